@@ -0,0 +1,114 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+
+use super::Identity;
+
+#[cfg(feature = "server")]
+use super::{get_owner_from_session, get_sid_from_session, set_session_owner, store, UserRecord};
+
+/// Register the user account, that owns the cats of this session
+#[post("/api/v1/account/register", session: tower_sessions::Session)]
+pub async fn register(name: String, password: String) -> Result<()> {
+    let owner = get_owner_from_session(&session).await?;
+    let name = check_name(&name)?;
+    check_password(&password)?;
+    if store().user_of_owner(&owner).await?.is_some() {
+        return Err(anyhow::anyhow!("this device is already logged in"));
+    }
+    let password_hash = tokio::task::spawn_blocking(move || hash_password(&password)).await??;
+    let user = UserRecord {
+        name,
+        password_hash,
+        owner,
+    };
+    if !store().create_user(&user).await? {
+        return Err(anyhow::anyhow!("the name is already used"));
+    }
+    Ok(())
+}
+
+/// Log in the user account, this session is of the owner of it
+///
+/// If `attach` is true, the cats of this session are moved to the account.
+/// Return the identity of the owner of the account, as `SessionCheck::identity`.
+#[post("/api/v1/account/login", session: tower_sessions::Session)]
+pub async fn login(name: String, password: String, attach: bool) -> Result<Identity> {
+    let owner = get_owner_from_session(&session).await?;
+    let user = store().find_user(name.trim()).await?;
+    let password_hash = user.as_ref().map(|u| u.password_hash.clone());
+    let is_valid = tokio::task::spawn_blocking(move || match password_hash {
+        Some(password_hash) => verify_password(&password, &password_hash),
+        None => {
+            // The same time as a known name, against the enumeration of names
+            let _ = hash_password(&password);
+            false
+        }
+    })
+    .await?;
+    let Some(user) = user.filter(|_| is_valid) else {
+        return Err(anyhow::anyhow!("invalid name or password"));
+    };
+    // The cats of the other account are never moved
+    if attach && store().user_of_owner(&owner).await?.is_none() {
+        let count = store().merge_owner(&owner, &user.owner).await?;
+        dioxus_logger::tracing::info!("attached {count} cats to the account");
+    }
+    set_session_owner(&session, &user.owner).await
+}
+
+/// Log out, the session and the device session are deleted
+#[post("/api/v1/account/logout", session: tower_sessions::Session)]
+pub async fn logout() -> Result<()> {
+    let owner = get_owner_from_session(&session).await?;
+    let sid = get_sid_from_session(&session).await?;
+    store().delete_device_session(&owner, &sid).await?;
+    session.flush().await?;
+    Ok(())
+}
+
+/// Return the name of the user account of this session, if it is logged in
+#[get("/api/v1/account", session: tower_sessions::Session)]
+pub async fn current_user() -> Result<Option<String>> {
+    let owner = get_owner_from_session(&session).await?;
+    store().user_of_owner(&owner).await
+}
+
+#[cfg(feature = "server")]
+fn check_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 64 || name.chars().any(char::is_control) {
+        return Err(anyhow::anyhow!("the name must be 1 to 64 characters"));
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(feature = "server")]
+fn check_password(password: &str) -> Result<()> {
+    if password.chars().count() < 8 {
+        return Err(anyhow::anyhow!(
+            "the password must be at least 8 characters"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+fn hash_password(password: &str) -> Result<String> {
+    use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = argon2::Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash the password: {e}"))?;
+    Ok(hash.to_string())
+}
+
+#[cfg(feature = "server")]
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+    match PasswordHash::new(password_hash) {
+        Ok(hash) => argon2::Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(_e) => false,
+    }
+}
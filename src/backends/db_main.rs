@@ -1,177 +1,458 @@
 use anyhow::Result;
+use browserinfocm::browserinfo::Browser;
 use dioxus::prelude::*;
 
 #[cfg(feature = "server")]
-use std::path::PathBuf;
+use super::{
+    cache_image_later, is_legacy_bicmid, issue_token, new_identity, new_sid, owner_key, store,
+    verify_token, CatStore,
+};
 
-#[cfg(feature = "server")]
-use dioxus::fullstack::Lazy;
+/// the cat in the list of favorites
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CatItem {
+    pub id: i64,
+    pub url: String,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    /// the hash of the cached image, then the thumbnails are available
+    pub image_hash: Option<String>,
+    /// the url that the image is saved from
+    #[serde(default)]
+    pub source_url: String,
+    /// the time of the save, `YYYY-MM-DD HH:MM:SS` in UTC
+    #[serde(default)]
+    pub create_at: String,
+    /// the tags, in ascending order
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// the name of the album, that the cat is in
+    #[serde(default)]
+    pub album: Option<String>,
+}
 
-#[cfg(feature = "server")]
-use sqlx::Row;
+/// the cat to save, with the metadata from the provider
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NewCat {
+    pub url: String,
+    /// the name of the provider, e.g. `thecatapi`, empty if it is unknown
+    pub provider: String,
+    /// the image id in the provider
+    pub provider_id: Option<String>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    /// the names of the breeds, if the provider knows them
+    pub breeds: Vec<String>,
+}
 
-// The database is only available to server code
-#[cfg(feature = "server")]
-static DB: Lazy<sqlx::SqlitePool> = Lazy::new(|| async move {
-    let pool = create_sqlx_pool().await?;
-    dioxus::Ok(pool)
-});
+impl NewCat {
+    /// Create the cat of only the url, that the provider is unknown
+    #[cfg(feature = "server")]
+    pub(crate) fn from_url(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// the identity of the owner, that the client keeps
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Identity {
+    /// the signed token, that restores the identity over the expiry of the session
+    /// and the restart of the app
+    pub token: String,
+    /// the key of the owner, that the changes on this device are kept by
+    pub owner_key: String,
+}
+
+impl Identity {
+    /// Create the identity of `owner` on the device session `sid`
+    #[cfg(feature = "server")]
+    pub(crate) fn new(owner: &str, sid: &str) -> Self {
+        Self {
+            token: issue_token(owner, sid),
+            owner_key: owner_key(owner),
+        }
+    }
+}
+
+/// the result of `check_session()`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SessionCheck {
+    /// true, if the session was rejected and re-established
+    pub is_renewed: bool,
+    /// the identity of the owner of this session
+    pub identity: Identity,
+}
+
+/// Establish the session of the owner of cats
+///
+/// The owner is the identity issued by the server, `bicmid` is only a hint.
+/// The identity is of the session, or else of `token` that the client keeps,
+/// or else a new one is issued.
+/// The session is rejected and re-established, if `bicmid` is not the one of the session,
+/// or if the device session is revoked.
+#[post("/api/v1/session", session: tower_sessions::Session)]
+pub async fn check_session(
+    bicmid: String,
+    token: Option<String>,
+    browser: Browser,
+) -> Result<SessionCheck> {
+    let session_bicmid = session.get::<String>("bicmid").await?;
+    let session_sid = session.get::<String>("sid").await?;
+    if let (Some(session_bicmid), Some(sid)) = (&session_bicmid, &session_sid) {
+        if session_bicmid.as_str() == bicmid.as_str() {
+            if let Some(owner) = store().touch_device_session(sid).await? {
+                session.insert("owner", &owner).await?;
+                return Ok(SessionCheck {
+                    is_renewed: false,
+                    identity: Identity::new(&owner, sid),
+                });
+            }
+        }
+    }
+    // The session of the time before the identity proves the ownership of its bicmid
+    let legacy_bicmid = match (&session_bicmid, &session_sid) {
+        (Some(b), None) if *b == bicmid && is_legacy_bicmid(b) => Some(bicmid.as_str()),
+        _ => None,
+    };
+    let is_renewed = match session_bicmid {
+        Some(_) if legacy_bicmid.is_none() => {
+            dioxus_logger::tracing::info!("reject the session: the bicmid mismatch or revoked");
+            session.flush().await?;
+            true
+        }
+        _ => {
+            // The new id of the session against the session fixation
+            session.cycle_id().await?;
+            false
+        }
+    };
+    let restored = match token.as_deref().and_then(verify_token) {
+        // The device session may be revoked, or linked to the other owner since the token
+        Some((_, sid)) => store()
+            .touch_device_session(&sid)
+            .await?
+            .map(|owner| (owner, sid)),
+        None => None,
+    };
+    let (owner, sid) = match restored {
+        Some(r) => r,
+        None => new_device_session(legacy_bicmid, &browser).await?,
+    };
+    session.insert("bicmid", &bicmid).await?;
+    session.insert("owner", &owner).await?;
+    session.insert("sid", &sid).await?;
+    Ok(SessionCheck {
+        is_renewed,
+        identity: Identity::new(&owner, &sid),
+    })
+}
 
+/// Create the device session of a new identity, and return the owner and the device session
+///
+/// The cats of `legacy_bicmid`, that is proved by the session, are claimed by the identity.
 #[cfg(feature = "server")]
-async fn create_sqlx_pool() -> Result<sqlx::sqlite::SqlitePool> {
-    use sqlx::sqlite::SqliteConnectOptions;
-    use sqlx::sqlite::SqlitePoolOptions;
-    use std::str::FromStr;
-    //
-    let db_path = get_db_path_();
-    let sq_uri = format!("sqlite://{}", db_path.display());
-    // Open the database from the persisted "cattongue.sqlite3" file
-    let opts = SqliteConnectOptions::from_str(&sq_uri)?.create_if_missing(true);
-    let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect_with(opts)
+async fn new_device_session(
+    legacy_bicmid: Option<&str>,
+    browser: &Browser,
+) -> Result<(String, String)> {
+    let owner = new_identity();
+    // The cats of the time before the identity are claimed only once
+    if let Some(bicmid) = legacy_bicmid {
+        if store().rename_owner(bicmid, &owner).await? {
+            dioxus_logger::tracing::info!("claimed the cats of the legacy bicmid");
+        }
+    }
+    let sid = new_sid();
+    let (browser, os) = device_of_browser(browser);
+    store()
+        .create_device_session(&sid, &owner, &browser, &os)
         .await?;
-    // Create tables if it doesn't already exist
-    create_tables(&pool).await?;
-    Ok(pool)
+    Ok((owner, sid))
 }
 
+/// Return the names of the browser and the os, for the list of sessions
 #[cfg(feature = "server")]
-fn get_db_path_() -> PathBuf {
-    let key1 = "CATTONGUE_DB_PATH";
-    if let Ok(s) = std::env::var(key1) {
-        return PathBuf::from(s);
+fn device_of_browser(browser: &Browser) -> (String, String) {
+    let mut name = format!("{} {}", browser.name, browser.version);
+    if !browser.device.is_empty() {
+        name = format!("{name} ({})", browser.device);
     }
-    let mut data_dir = super::data_base_dir();
-    let key3 = "CATTONGUE_DB_FILE";
-    let db_file = if let Ok(s) = std::env::var(key3) {
-        s
-    } else {
-        "cattongue.sqlite3".to_string()
+    let os = match &browser.os {
+        Some(os) => format!("{} {}", os.name, os.version),
+        None => String::new(),
     };
-    data_dir.push(db_file);
-    data_dir
+    let cut = |s: String| s.trim().chars().take(100).collect::<String>();
+    (cut(name), cut(os))
 }
 
-#[post("/api/v1/session", session: tower_sessions::Session)]
-pub async fn check_session(bicmid: String) -> Result<bool> {
-    if let Some(session_bicmid) = session.get::<String>("bicmid").await? {
-        dioxus_logger::tracing::debug!("bicmid: '{}' '{}'", &session_bicmid, &bicmid);
-        Ok(bicmid.as_str() == session_bicmid.as_str())
-    } else {
-        dioxus_logger::tracing::debug!("insert bicmid: '{}'", &bicmid);
-        session.insert("bicmid", &bicmid).await?;
-        Ok(true)
+/// Return the owner of cats of the session, that is established by `check_session()`
+///
+/// The last seen time of the device session is updated,
+/// and the session is deleted if the device session is revoked.
+#[cfg(feature = "server")]
+pub(crate) async fn get_owner_from_session(session: &tower_sessions::Session) -> Result<String> {
+    let sid = get_sid_from_session(session).await?;
+    let Some(owner) = store().touch_device_session(&sid).await? else {
+        session.flush().await?;
+        return Err(anyhow::anyhow!("the session is revoked"));
+    };
+    // The device session follows the owner, e.g. linked by the other device
+    if session.get::<String>("owner").await?.as_deref() != Some(owner.as_str()) {
+        session.insert("owner", &owner).await?;
     }
+    Ok(owner)
 }
 
+/// Return the device session of the session
 #[cfg(feature = "server")]
-async fn get_bicmid_from_session(session: &tower_sessions::Session) -> Result<String> {
-    if let Some(bicmid) = session.get::<String>("bicmid").await? {
-        Ok(bicmid)
-    } else {
-        Err(anyhow::anyhow!("Failed to get the bicmid from session"))
+pub(crate) async fn get_sid_from_session(session: &tower_sessions::Session) -> Result<String> {
+    match session.get::<String>("sid").await? {
+        Some(sid) => Ok(sid),
+        None => Err(anyhow::anyhow!("Failed to get the owner from session")),
     }
 }
 
-/// Query the database and return the last 20 cats and their url
-#[get("/api/v1/cats?off=offset" , session: tower_sessions::Session)]
-pub async fn list_cats(offset: usize) -> Result<Vec<(i64, String)>> {
-    let bicmid = get_bicmid_from_session(&session).await?;
-    let offset: i64 = offset.try_into()?;
-    let r = {
-        let mut tx = DB.begin().await?;
-        //
-        let cats = sqlx::query(concat!(
-            r#"SELECT Cat.id, UrlOrigin.value, Cat.url_path FROM Cat"#,
-            r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
-            r#" INNER JOIN UrlOrigin ON Cat.url_origin_id = UrlOrigin.id"#,
-            r#" WHERE Bicmid.value = ?"#,
-            r#" ORDER BY Cat.id DESC LIMIT 20 OFFSET ?"#
-        ))
-        .bind(bicmid)
-        .bind(offset)
-        .fetch_all(&mut *tx)
-        .await?
-        .iter()
-        .map(|row| {
-            (
-                row.get::<i64, _>(0),
-                format!("{}{}", row.get::<String, _>(1), row.get::<String, _>(2)),
-            )
-        })
-        .collect();
-        //
-        tx.commit().await?;
-        cats
+/// Change the owner of the session and the device session, and return the new identity
+#[cfg(feature = "server")]
+pub(crate) async fn set_session_owner(
+    session: &tower_sessions::Session,
+    owner: &str,
+) -> Result<Identity> {
+    let sid = get_sid_from_session(session).await?;
+    store().set_device_session_owner(&sid, owner).await?;
+    // The new id of the session against the session fixation
+    session.cycle_id().await?;
+    session.insert("owner", owner).await?;
+    Ok(Identity::new(owner, &sid))
+}
+
+/// a page of favorites, the result of `list_cats_page()`
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CatsPage {
+    /// the cats of the page, the newest first
+    pub items: Vec<CatItem>,
+    /// the count of all cats
+    pub total: usize,
+    /// the cursor of the page of the older cats, if they exist
+    pub next_cursor: Option<String>,
+    /// the cursor of the page of the newer cats, if they exist
+    pub prev_cursor: Option<String>,
+}
+
+/// the position of a page of favorites, that is keyed by `Cat.id`
+#[cfg(feature = "server")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CatCursor {
+    /// the newest cats
+    First,
+    /// the cats older than the id
+    Older(i64),
+    /// the cats newer than the id
+    Newer(i64),
+}
+
+#[cfg(feature = "server")]
+impl CatCursor {
+    /// Return the cursor, that is opaque for the client
+    fn encode(&self) -> Option<String> {
+        match self {
+            Self::First => None,
+            Self::Older(id) => Some(format!("o{id:x}")),
+            Self::Newer(id) => Some(format!("n{id:x}")),
+        }
+    }
+
+    fn decode(s: &str) -> Result<Self> {
+        let invalid = || anyhow::anyhow!("invalid cursor: '{s}'");
+        let id = s
+            .get(1..)
+            .and_then(|hex| i64::from_str_radix(hex, 16).ok())
+            .ok_or_else(invalid)?;
+        match s.as_bytes().first() {
+            Some(b'o') => Ok(Self::Older(id)),
+            Some(b'n') => Ok(Self::Newer(id)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// the largest page size of `list_cats_page()`
+#[cfg(feature = "server")]
+pub(crate) const MAX_PAGE_SIZE: usize = 100;
+
+/// Query the database and return the page of favorites at `cursor`, or the first page
+///
+/// The page size is `limit`, or `favorites.page_size` of the configuration.
+#[post("/api/v1/cats/page" , session: tower_sessions::Session)]
+pub async fn list_cats_page(cursor: Option<String>, limit: Option<usize>) -> Result<CatsPage> {
+    let owner = get_owner_from_session(&session).await?;
+    let limit = limit
+        .unwrap_or(super::config().favorites.page_size)
+        .clamp(1, MAX_PAGE_SIZE);
+    let cursor = match cursor.as_deref() {
+        Some(s) => CatCursor::decode(s)?,
+        None => CatCursor::First,
     };
+    let r = cats_page(store(), &owner, cursor, limit).await?;
     //
-    #[cfg(feature = "backend_delay")]
-    let _ = sleep_x(2000).await;
+    backend_delay().await;
     //
     Ok(r)
 }
 
+/// Return the page of favorites of `owner` at `cursor` in `store`
+#[cfg(feature = "server")]
+pub(crate) async fn cats_page(
+    store: &dyn CatStore,
+    owner: &str,
+    cursor: CatCursor,
+    limit: usize,
+) -> Result<CatsPage> {
+    let total = store.count_of_cats(owner).await?;
+    let mut r = cats_page_at(store, owner, cursor, limit).await?;
+    // The page is gone by the deletion, or the newer page is short, then the first page
+    if cursor != CatCursor::First && (r.items.is_empty() || r.prev_cursor.is_none()) {
+        r = cats_page_at(store, owner, CatCursor::First, limit).await?;
+    }
+    r.total = total;
+    Ok(r)
+}
+
+#[cfg(feature = "server")]
+async fn cats_page_at(
+    store: &dyn CatStore,
+    owner: &str,
+    cursor: CatCursor,
+    limit: usize,
+) -> Result<CatsPage> {
+    let exists = |cursor: CatCursor| async move {
+        let cats = store.list_cats_page(owner, cursor, 1).await?;
+        Ok::<bool, anyhow::Error>(!cats.is_empty())
+    };
+    // The one more cat tells that the next page in the direction exists
+    let mut items = store
+        .list_cats_page(owner, cursor, limit as i64 + 1)
+        .await?;
+    let is_more = items.len() > limit;
+    let (has_newer, has_older) = match cursor {
+        CatCursor::First => {
+            items.truncate(limit);
+            (false, is_more)
+        }
+        CatCursor::Older(_) => {
+            items.truncate(limit);
+            let has_newer = match items.first() {
+                Some(cat) => exists(CatCursor::Newer(cat.id)).await?,
+                None => false,
+            };
+            (has_newer, is_more)
+        }
+        CatCursor::Newer(_) => {
+            if is_more {
+                items.remove(0);
+            }
+            let has_older = match items.last() {
+                Some(cat) => exists(CatCursor::Older(cat.id)).await?,
+                None => false,
+            };
+            (is_more, has_older)
+        }
+    };
+    let prev_cursor = match (has_newer, items.first()) {
+        (true, Some(cat)) => CatCursor::Newer(cat.id).encode(),
+        _ => None,
+    };
+    let next_cursor = match (has_older, items.last()) {
+        (true, Some(cat)) => CatCursor::Older(cat.id).encode(),
+        _ => None,
+    };
+    Ok(CatsPage {
+        items,
+        total: 0,
+        next_cursor,
+        prev_cursor,
+    })
+}
+
 /// Query the database and return the count of cats
 #[post("/api/v1/count_of_cats" , session: tower_sessions::Session)]
 pub async fn count_of_cats(_x: String) -> Result<usize> {
-    let bicmid = get_bicmid_from_session(&session).await?;
-    let r = {
-        let mut tx = DB.begin().await?;
-        //
-        let r = sqlx::query(concat!(
-            r#"SELECT count(*) FROM Cat"#,
-            r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
-            r#" WHERE Bicmid.value = ?"#
-        ))
-        .bind(bicmid)
-        .fetch_one(&mut *tx)
-        .await?
-        .get::<i64, _>(0) as usize;
-        //
-        tx.commit().await?;
-        r
-    };
+    let owner = get_owner_from_session(&session).await?;
+    let r = store().count_of_cats(&owner).await?;
     //
-    #[cfg(feature = "backend_delay")]
-    let _ = sleep_x(2000).await;
+    backend_delay().await;
     //
     Ok(r)
 }
 
-/// Query the database and delete the cat
+/// Query the database and move the cat to the trash
 #[delete("/api/v1/cats/{id}" , session: tower_sessions::Session)]
 pub async fn delete_cat(id: i64) -> Result<()> {
-    let bicmid = get_bicmid_from_session(&session).await?;
-    {
-        let mut tx = DB.begin().await?;
-        //
-        sqlx::query(concat!(
-            r#"DELETE FROM Cat"#,
-            r#" WHERE id IN ("#,
-            r#" SELECT Cat.id FROM Cat"#,
-            r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
-            r#" WHERE Bicmid.value = ? AND Cat.id = ?"#,
-            r#" )"#,
-        ))
-        .bind(bicmid)
-        .bind(id)
-        .execute(&mut *tx)
-        .await?;
-        //
-        tx.commit().await?;
-    }
+    let owner = get_owner_from_session(&session).await?;
+    store().delete_cats(&owner, &[id]).await?;
     //
-    #[cfg(feature = "backend_delay")]
-    let _ = sleep_x(2000).await;
+    backend_delay().await;
     //
     Ok(())
 }
 
+/// the largest count of ids of the bulk actions
+pub const MAX_BULK_IDS: usize = 1000;
+
+/// Return an error, if `ids` is over `MAX_BULK_IDS`
+#[cfg(feature = "server")]
+pub(crate) fn check_bulk_ids(ids: &[i64]) -> Result<()> {
+    if ids.len() > MAX_BULK_IDS {
+        return Err(anyhow::anyhow!(
+            "too many cats: {}, the limit is {MAX_BULK_IDS}",
+            ids.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Query the database and move the cats of `ids` to the trash in a transaction
+///
+/// Return the count of moved cats.
+#[post("/api/v1/cats/delete" , session: tower_sessions::Session)]
+pub async fn delete_cats(ids: Vec<i64>) -> Result<u64> {
+    let owner = get_owner_from_session(&session).await?;
+    check_bulk_ids(&ids)?;
+    let r = store().delete_cats(&owner, &ids).await?;
+    //
+    backend_delay().await;
+    //
+    Ok(r)
+}
+
+/// the result of `save_cat()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SaveStatus {
+    Saved,
+    AlreadySaved,
+}
+
+impl SaveStatus {
+    #[cfg(feature = "server")]
+    pub(crate) fn from_rows_affected(rows: u64) -> Self {
+        if rows > 0 {
+            Self::Saved
+        } else {
+            Self::AlreadySaved
+        }
+    }
+}
+
 /// Query the database and save the cat
 #[post("/api/v1/cats" , session: tower_sessions::Session)]
-pub async fn save_cat(image: String) -> Result<()> {
-    let bicmid = get_bicmid_from_session(&session).await?;
+pub async fn save_cat(cat: NewCat) -> Result<SaveStatus> {
+    let owner = get_owner_from_session(&session).await?;
+    if !is_cat_url(&cat.url) {
+        return Err(anyhow::anyhow!("invalid url of the cat: '{}'", cat.url));
+    }
     #[cfg(feature = "backend_text")]
     {
         use std::io::Write;
@@ -184,134 +465,171 @@ pub async fn save_cat(image: String) -> Result<()> {
             .open("cattongue.txt")
             .unwrap();
         // And then write a newline to it with the image url
-        let _ = file.write_fmt(format_args!("{image}\n"));
+        let _ = file.write_fmt(format_args!("{}\n", cat.url));
     }
     //
-    let (url_origin, url_path) = {
-        let url = image.as_str();
-        if let Some(off1) = url.find("//") {
-            if let Some(off2) = url[(off1 + 2)..].find("/") {
-                (&url[..(off1 + 2 + off2)], &url[(off1 + 2 + off2)..])
-            } else {
-                (url, "")
-            }
-        } else {
-            (url, "")
-        }
-    };
-    loop {
-        let mut tx = DB.begin().await?;
-        //
-        let bicmid_id = get_or_store_bicmid(&mut tx, &bicmid).await?;
-        if bicmid_id == -1 {
-            tx.rollback().await?;
-            break;
-        }
-        //
-        let url_origin_id = get_or_store_url_origin(&mut tx, &url_origin).await?;
-        if url_origin_id == -1 {
-            tx.rollback().await?;
-            break;
-        }
-        //
-        sqlx::query(concat!(
-            r#"INSERT INTO Cat"#,
-            r#" (bicmid_id, url_origin_id, url_path)"#,
-            r#" VALUES (?, ?, ?)"#
-        ))
-        .bind(&bicmid_id)
-        .bind(&url_origin_id)
-        .bind(&url_path)
-        .execute(&mut *tx)
-        .await?;
-        //
-        tx.commit().await?;
-        break;
+    let r = store().save_cat(&owner, &cat).await?;
+    if r == SaveStatus::Saved {
+        cache_image_later(cat.url);
     }
     //
-    #[cfg(feature = "backend_delay")]
-    let _ = sleep_x(2000).await;
+    backend_delay().await;
     //
-    Ok(())
+    Ok(r)
 }
 
-#[cfg(feature = "backend_delay")]
-async fn sleep_x(millis: u64) -> Result<()> {
-    async_std::task::sleep(std::time::Duration::from_millis(millis)).await;
-    Ok(())
+// The delay of `debug.delay_ms`, to see the loading on the development
+#[cfg(feature = "server")]
+pub(crate) async fn backend_delay() {
+    let millis = super::config().debug.delay_ms;
+    if millis > 0 {
+        tokio::time::sleep(std::time::Duration::from_millis(millis)).await;
+    }
 }
 
+/// Return true, if `url` can be saved as the url of a cat
+///
+/// It is the http url with the path, or the image of the `local` provider.
 #[cfg(feature = "server")]
-macro_rules! simple_get_or_store {
-    ($func:ident, $tbl: expr) => {
-        async fn $func(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, val: &str) -> Result<i64> {
-            let mut tbl_id = -1;
-            let r = sqlx::query(concat!(r#"SELECT id FROM "#, $tbl, r#" WHERE value = ?"#))
-                .bind(val)
-                .fetch_one(&mut **tx)
-                .await;
-            if let Ok(row) = r {
-                tbl_id = row.get(0);
-            } else if let Err(sqlx::Error::RowNotFound) = r {
-                let r = sqlx::query(concat!(r#"INSERT INTO "#, $tbl, r#" (value) VALUES (?)"#))
-                    .bind(val)
-                    .execute(&mut **tx)
-                    .await?;
-                tbl_id = r.last_insert_rowid();
-            } else if let Err(e) = r {
-                return Err(e.into());
-            }
-            Ok(tbl_id)
-        }
-    };
+pub(crate) fn is_cat_url(url: &str) -> bool {
+    const LOCAL_CATS: &str = "/api/v1/local_cats/";
+    if url.starts_with('/') {
+        return url.starts_with(LOCAL_CATS) && url.len() > LOCAL_CATS.len();
+    }
+    let is_http = url.starts_with("https://") || url.starts_with("http://");
+    let (_url_origin, url_path) = split_url(url);
+    is_http && url_path.len() > 1
 }
 
+/// Split the url into the origin and the path
 #[cfg(feature = "server")]
-simple_get_or_store!(get_or_store_bicmid, "Bicmid");
+pub(crate) fn split_url(url: &str) -> (&str, &str) {
+    if let Some(off1) = url.find("//") {
+        if let Some(off2) = url[(off1 + 2)..].find("/") {
+            (&url[..(off1 + 2 + off2)], &url[(off1 + 2 + off2)..])
+        } else {
+            (url, "")
+        }
+    } else if url.starts_with('/') {
+        // the url that is relative to this server, e.g. the `local` provider
+        ("", url)
+    } else {
+        (url, "")
+    }
+}
 
+#[cfg(test)]
 #[cfg(feature = "server")]
-simple_get_or_store!(get_or_store_url_origin, "UrlOrigin");
+mod tests {
+    use super::*;
+    use crate::backends::store_memory::MemoryCatStore;
 
-// Create tables if it doesn't already exist
-#[cfg(feature = "server")]
-async fn create_tables(pool: &sqlx::sqlite::SqlitePool) -> Result<()> {
-    // table: `Cat`, `Bicmid`, `UrlOrigin`
-    const SQL: &str = concat!(
-        r#"CREATE TABLE IF NOT EXISTS Cat ("#,
-        r#" id INTEGER PRIMARY KEY,"#,
-        r#" bicmid_id INTEGER NOT NULL,"#,
-        r#" create_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,"#,
-        r#" url_origin_id INTEGER NOT NULL,"#,
-        r#" url_path  TEXT NOT NULL"#,
-        r#");"#,
-        "\n",
-        r#"CREATE INDEX IF NOT EXISTS Cat_bicmid_id ON Cat (bicmid_id);"#,
-        "\n",
-        r#"CREATE TABLE IF NOT EXISTS Bicmid ("#,
-        r#" id INTEGER PRIMARY KEY AUTOINCREMENT,"#,
-        r#" create_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,"#,
-        r#" value TEXT NOT NULL"#,
-        r#");"#,
-        "\n",
-        r#"CREATE UNIQUE INDEX IF NOT EXISTS Bicmid_value ON Bicmid (value);"#,
-        "\n",
-        r#"INSERT INTO Bicmid (id, value)"#,
-        r#" SELECT * FROM (SELECT 0, '') AS Bicmid"#,
-        r#" WHERE NOT EXISTS (SELECT * FROM Bicmid WHERE id = 0);"#,
-        "\n",
-        r#"CREATE TABLE IF NOT EXISTS UrlOrigin ("#,
-        r#" id INTEGER PRIMARY KEY AUTOINCREMENT,"#,
-        r#" create_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,"#,
-        r#" value TEXT NOT NULL"#,
-        r#");"#,
-        "\n",
-        r#"CREATE UNIQUE INDEX IF NOT EXISTS UrlOrigin_value ON UrlOrigin (value);"#,
-        "\n",
-        r#"INSERT INTO UrlOrigin (id, value)"#,
-        r#" SELECT * FROM (SELECT 0, '') AS UrlOrigin"#,
-        r#" WHERE NOT EXISTS (SELECT * FROM UrlOrigin WHERE id = 0);"#,
-        "\n",
-    );
-    sqlx::query(SQL).execute(pool).await?;
-    Ok(())
+    // The store of `owner` with the cats of `count`, and the ids of them, the newest first
+    async fn store_with(count: usize) -> (MemoryCatStore, Vec<i64>) {
+        let store = MemoryCatStore::new();
+        for i in 0..count {
+            let cat = NewCat::from_url(&format!("https://example.com/{i}.png"));
+            store.save_cat("owner", &cat).await.unwrap();
+        }
+        let cats = store.list_cats_page("owner", CatCursor::First, 100).await;
+        let ids = cats.unwrap().iter().map(|cat| cat.id).collect();
+        (store, ids)
+    }
+
+    fn ids(page: &CatsPage) -> Vec<i64> {
+        page.items.iter().map(|cat| cat.id).collect()
+    }
+
+    #[test]
+    fn cursor_roundtrip() {
+        for cursor in [CatCursor::Older(0x1f), CatCursor::Newer(1)] {
+            let s = cursor.encode().unwrap();
+            assert_eq!(CatCursor::decode(&s).unwrap(), cursor);
+        }
+        assert_eq!(CatCursor::First.encode(), None);
+        for s in ["", "o", "x1f", "ozz", "n-"] {
+            assert!(CatCursor::decode(s).is_err(), "{s}");
+        }
+    }
+
+    #[tokio::test]
+    async fn cats_page_steps_both_ways() {
+        let (store, all) = store_with(5).await;
+        let page = cats_page(&store, "owner", CatCursor::First, 2)
+            .await
+            .unwrap();
+        assert_eq!((ids(&page), page.total), (all[0..2].to_vec(), 5));
+        assert_eq!(page.prev_cursor, None);
+        //
+        let next = CatCursor::decode(page.next_cursor.as_deref().unwrap()).unwrap();
+        let page = cats_page(&store, "owner", next, 2).await.unwrap();
+        assert_eq!(ids(&page), all[2..4]);
+        let next = CatCursor::decode(page.next_cursor.as_deref().unwrap()).unwrap();
+        let last = cats_page(&store, "owner", next, 2).await.unwrap();
+        assert_eq!(ids(&last), all[4..]);
+        assert_eq!(last.next_cursor, None);
+        // Back to the newer pages
+        let prev = CatCursor::decode(last.prev_cursor.as_deref().unwrap()).unwrap();
+        let page = cats_page(&store, "owner", prev, 2).await.unwrap();
+        assert_eq!(ids(&page), all[2..4]);
+        let prev = CatCursor::decode(page.prev_cursor.as_deref().unwrap()).unwrap();
+        let page = cats_page(&store, "owner", prev, 2).await.unwrap();
+        assert_eq!(ids(&page), all[0..2]);
+        assert_eq!(page.prev_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn cats_page_falls_back_to_the_first() {
+        let (store, all) = store_with(5).await;
+        // The page is gone by the deletion
+        store.delete_cats("owner", &all[3..]).await.unwrap();
+        let page = cats_page(&store, "owner", CatCursor::Older(all[2]), 2).await;
+        let page = page.unwrap();
+        assert_eq!((ids(&page), page.total), (all[0..2].to_vec(), 3));
+        // The newer page is short
+        let page = cats_page(&store, "owner", CatCursor::Newer(all[1]), 2).await;
+        assert_eq!(ids(&page.unwrap()), all[0..2]);
+        // No cat
+        let page = cats_page(&store, "nobody", CatCursor::Older(9), 2).await;
+        assert_eq!(page.unwrap(), CatsPage::default());
+    }
+
+    #[test]
+    fn bulk_ids_are_limited() {
+        assert!(check_bulk_ids(&[]).is_ok());
+        assert!(check_bulk_ids(&vec![1; MAX_BULK_IDS]).is_ok());
+        assert!(check_bulk_ids(&vec![1; MAX_BULK_IDS + 1]).is_err());
+    }
+
+    #[test]
+    fn url_is_split_at_the_path() {
+        let url = "https://cdn2.thecatapi.com/images/a1.jpg";
+        assert_eq!(
+            split_url(url),
+            ("https://cdn2.thecatapi.com", "/images/a1.jpg")
+        );
+        assert_eq!(
+            split_url("/api/v1/local/c.png"),
+            ("", "/api/v1/local/c.png")
+        );
+        assert_eq!(
+            split_url("https://example.com"),
+            ("https://example.com", "")
+        );
+    }
+
+    #[test]
+    fn cat_url_is_checked() {
+        assert!(is_cat_url("https://cdn2.thecatapi.com/images/a1.jpg"));
+        assert!(is_cat_url("http://example.com/a"));
+        assert!(is_cat_url("/api/v1/local_cats/c.png"));
+        assert!(!is_cat_url(""));
+        assert!(!is_cat_url("https://example.com"));
+        assert!(!is_cat_url("https://example.com/"));
+        assert!(!is_cat_url("ftp://example.com/a.png"));
+        assert!(!is_cat_url("javascript:alert(1)"));
+        assert!(!is_cat_url("/api/v1/local_cats/"));
+        assert!(!is_cat_url("/etc/passwd"));
+        assert!(!is_cat_url("//example.com/a.png"));
+    }
 }
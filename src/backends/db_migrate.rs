@@ -0,0 +1,509 @@
+use anyhow::Result;
+
+use sqlx::Row;
+
+/// a step of the schema migration
+///
+/// Each step has the same schema in the dialect of each database.
+pub(crate) struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sqlite: &'static str,
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    pub postgres: &'static str,
+}
+
+// The migration steps of the main database, in ascending order of `version`.
+// Never edit a released step, append a new one instead.
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "table: `Cat`, `Bicmid`, `UrlOrigin`",
+        sqlite: concat!(
+            r#"CREATE TABLE IF NOT EXISTS Cat ("#,
+            r#" id INTEGER PRIMARY KEY,"#,
+            r#" bicmid_id INTEGER NOT NULL,"#,
+            r#" create_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,"#,
+            r#" url_origin_id INTEGER NOT NULL,"#,
+            r#" url_path  TEXT NOT NULL"#,
+            r#");"#,
+            "\n",
+            r#"CREATE INDEX IF NOT EXISTS Cat_bicmid_id ON Cat (bicmid_id);"#,
+            "\n",
+            r#"CREATE TABLE IF NOT EXISTS Bicmid ("#,
+            r#" id INTEGER PRIMARY KEY AUTOINCREMENT,"#,
+            r#" create_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,"#,
+            r#" value TEXT NOT NULL"#,
+            r#");"#,
+            "\n",
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS Bicmid_value ON Bicmid (value);"#,
+            "\n",
+            r#"INSERT INTO Bicmid (id, value)"#,
+            r#" SELECT * FROM (SELECT 0, '') AS Bicmid"#,
+            r#" WHERE NOT EXISTS (SELECT * FROM Bicmid WHERE id = 0);"#,
+            "\n",
+            r#"CREATE TABLE IF NOT EXISTS UrlOrigin ("#,
+            r#" id INTEGER PRIMARY KEY AUTOINCREMENT,"#,
+            r#" create_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,"#,
+            r#" value TEXT NOT NULL"#,
+            r#");"#,
+            "\n",
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS UrlOrigin_value ON UrlOrigin (value);"#,
+            "\n",
+            r#"INSERT INTO UrlOrigin (id, value)"#,
+            r#" SELECT * FROM (SELECT 0, '') AS UrlOrigin"#,
+            r#" WHERE NOT EXISTS (SELECT * FROM UrlOrigin WHERE id = 0);"#,
+            "\n",
+        ),
+        postgres: concat!(
+            r#"CREATE TABLE IF NOT EXISTS Cat ("#,
+            r#" id BIGSERIAL PRIMARY KEY,"#,
+            r#" bicmid_id BIGINT NOT NULL,"#,
+            r#" create_at TEXT NOT NULL DEFAULT "#,
+            r#"to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),"#,
+            r#" url_origin_id BIGINT NOT NULL,"#,
+            r#" url_path  TEXT NOT NULL"#,
+            r#");"#,
+            "\n",
+            r#"CREATE INDEX IF NOT EXISTS Cat_bicmid_id ON Cat (bicmid_id);"#,
+            "\n",
+            r#"CREATE TABLE IF NOT EXISTS Bicmid ("#,
+            r#" id BIGSERIAL PRIMARY KEY,"#,
+            r#" create_at TEXT NOT NULL DEFAULT "#,
+            r#"to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),"#,
+            r#" value TEXT NOT NULL"#,
+            r#");"#,
+            "\n",
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS Bicmid_value ON Bicmid (value);"#,
+            "\n",
+            r#"INSERT INTO Bicmid (id, value) VALUES (0, '') ON CONFLICT DO NOTHING;"#,
+            "\n",
+            r#"CREATE TABLE IF NOT EXISTS UrlOrigin ("#,
+            r#" id BIGSERIAL PRIMARY KEY,"#,
+            r#" create_at TEXT NOT NULL DEFAULT "#,
+            r#"to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),"#,
+            r#" value TEXT NOT NULL"#,
+            r#");"#,
+            "\n",
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS UrlOrigin_value ON UrlOrigin (value);"#,
+            "\n",
+            r#"INSERT INTO UrlOrigin (id, value) VALUES (0, '') ON CONFLICT DO NOTHING;"#,
+            "\n",
+        ),
+    },
+    Migration {
+        version: 2,
+        description: "unique `Cat` per (bicmid_id, url_origin_id, url_path)",
+        sqlite: UNIQUE_CAT,
+        postgres: UNIQUE_CAT,
+    },
+    Migration {
+        version: 3,
+        description: "columns of the provider metadata into `Cat`",
+        sqlite: concat!(
+            r#"ALTER TABLE Cat ADD COLUMN provider TEXT NOT NULL DEFAULT '';"#,
+            "\n",
+            r#"ALTER TABLE Cat ADD COLUMN provider_id TEXT;"#,
+            "\n",
+            r#"ALTER TABLE Cat ADD COLUMN width INTEGER;"#,
+            "\n",
+            r#"ALTER TABLE Cat ADD COLUMN height INTEGER;"#,
+            "\n",
+            r#"ALTER TABLE Cat ADD COLUMN breeds TEXT NOT NULL DEFAULT '[]';"#,
+            "\n",
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS Cat_bicmid_id_provider_id"#,
+            r#" ON Cat (bicmid_id, provider, provider_id) WHERE provider_id IS NOT NULL;"#,
+            "\n",
+        ),
+        postgres: concat!(
+            r#"ALTER TABLE Cat ADD COLUMN provider TEXT NOT NULL DEFAULT '';"#,
+            "\n",
+            r#"ALTER TABLE Cat ADD COLUMN provider_id TEXT;"#,
+            "\n",
+            r#"ALTER TABLE Cat ADD COLUMN width BIGINT;"#,
+            "\n",
+            r#"ALTER TABLE Cat ADD COLUMN height BIGINT;"#,
+            "\n",
+            r#"ALTER TABLE Cat ADD COLUMN breeds TEXT NOT NULL DEFAULT '[]';"#,
+            "\n",
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS Cat_bicmid_id_provider_id"#,
+            r#" ON Cat (bicmid_id, provider, provider_id) WHERE provider_id IS NOT NULL;"#,
+            "\n",
+        ),
+    },
+    Migration {
+        version: 4,
+        description: "column of the hash of the cached image into `Cat`",
+        sqlite: IMAGE_HASH,
+        postgres: IMAGE_HASH,
+    },
+    Migration {
+        version: 5,
+        description: "table: `User`",
+        sqlite: concat!(
+            r#"CREATE TABLE IF NOT EXISTS "User" ("#,
+            r#" id INTEGER PRIMARY KEY AUTOINCREMENT,"#,
+            r#" create_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,"#,
+            r#" name TEXT NOT NULL,"#,
+            r#" password_hash TEXT NOT NULL,"#,
+            r#" owner TEXT NOT NULL"#,
+            r#");"#,
+            "\n",
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS User_name ON "User" (name);"#,
+            "\n",
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS User_owner ON "User" (owner);"#,
+            "\n",
+        ),
+        postgres: concat!(
+            r#"CREATE TABLE IF NOT EXISTS "User" ("#,
+            r#" id BIGSERIAL PRIMARY KEY,"#,
+            r#" create_at TEXT NOT NULL DEFAULT "#,
+            r#"to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),"#,
+            r#" name TEXT NOT NULL,"#,
+            r#" password_hash TEXT NOT NULL,"#,
+            r#" owner TEXT NOT NULL"#,
+            r#");"#,
+            "\n",
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS User_name ON "User" (name);"#,
+            "\n",
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS User_owner ON "User" (owner);"#,
+            "\n",
+        ),
+    },
+    Migration {
+        version: 6,
+        description: "table: `PairingCode`",
+        sqlite: concat!(
+            r#"CREATE TABLE IF NOT EXISTS PairingCode ("#,
+            r#" code TEXT PRIMARY KEY,"#,
+            r#" owner TEXT NOT NULL,"#,
+            r#" expire_at INTEGER NOT NULL"#,
+            r#");"#,
+            "\n",
+        ),
+        postgres: concat!(
+            r#"CREATE TABLE IF NOT EXISTS PairingCode ("#,
+            r#" code TEXT PRIMARY KEY,"#,
+            r#" owner TEXT NOT NULL,"#,
+            r#" expire_at BIGINT NOT NULL"#,
+            r#");"#,
+            "\n",
+        ),
+    },
+    Migration {
+        version: 7,
+        description: "table: `DeviceSession`",
+        sqlite: concat!(
+            r#"CREATE TABLE IF NOT EXISTS DeviceSession ("#,
+            r#" sid TEXT PRIMARY KEY,"#,
+            r#" owner TEXT NOT NULL,"#,
+            r#" browser TEXT NOT NULL DEFAULT '',"#,
+            r#" os TEXT NOT NULL DEFAULT '',"#,
+            r#" create_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,"#,
+            r#" last_seen_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP"#,
+            r#");"#,
+            "\n",
+            r#"CREATE INDEX IF NOT EXISTS DeviceSession_owner ON DeviceSession (owner);"#,
+            "\n",
+        ),
+        postgres: concat!(
+            r#"CREATE TABLE IF NOT EXISTS DeviceSession ("#,
+            r#" sid TEXT PRIMARY KEY,"#,
+            r#" owner TEXT NOT NULL,"#,
+            r#" browser TEXT NOT NULL DEFAULT '',"#,
+            r#" os TEXT NOT NULL DEFAULT '',"#,
+            r#" create_at TEXT NOT NULL DEFAULT "#,
+            r#"to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),"#,
+            r#" last_seen_at TEXT NOT NULL DEFAULT "#,
+            r#"to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')"#,
+            r#");"#,
+            "\n",
+            r#"CREATE INDEX IF NOT EXISTS DeviceSession_owner ON DeviceSession (owner);"#,
+            "\n",
+        ),
+    },
+    Migration {
+        version: 8,
+        description: "table: `Preference`",
+        sqlite: PREFERENCE,
+        postgres: PREFERENCE,
+    },
+    Migration {
+        version: 9,
+        description: "column: `Cat.deleted_at`",
+        sqlite: TRASH,
+        postgres: TRASH,
+    },
+    Migration {
+        version: 10,
+        description: "columns: `Cat.tags`, `Cat.album`",
+        sqlite: TAGS,
+        postgres: TAGS,
+    },
+];
+
+// The tags are the json array as `breeds`, and the cat is in one album at most
+const TAGS: &str = concat!(
+    r#"ALTER TABLE Cat ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';"#,
+    "\n",
+    r#"ALTER TABLE Cat ADD COLUMN album TEXT;"#,
+    "\n",
+);
+
+// The deleted cat is kept in the trash until it is purged
+const TRASH: &str = concat!(
+    r#"ALTER TABLE Cat ADD COLUMN deleted_at TEXT;"#,
+    "\n",
+    r#"CREATE INDEX IF NOT EXISTS Cat_deleted_at ON Cat (deleted_at);"#,
+    "\n",
+);
+
+// The preferences of the owner, e.g. the layout of the favorites
+const PREFERENCE: &str = concat!(
+    r#"CREATE TABLE IF NOT EXISTS Preference ("#,
+    r#" owner TEXT NOT NULL,"#,
+    r#" name TEXT NOT NULL,"#,
+    r#" value TEXT NOT NULL,"#,
+    r#" PRIMARY KEY (owner, name)"#,
+    r#");"#,
+    "\n",
+);
+
+// The image is cached in the content-addressed store by the hash
+const IMAGE_HASH: &str = concat!(r#"ALTER TABLE Cat ADD COLUMN image_hash TEXT;"#, "\n",);
+
+// Merge the duplicated cats into the first saved one, then make them unique
+const UNIQUE_CAT: &str = concat!(
+    r#"DELETE FROM Cat WHERE id NOT IN ("#,
+    r#" SELECT MIN(id) FROM Cat GROUP BY bicmid_id, url_origin_id, url_path"#,
+    r#");"#,
+    "\n",
+    r#"CREATE UNIQUE INDEX IF NOT EXISTS Cat_bicmid_id_url"#,
+    r#" ON Cat (bicmid_id, url_origin_id, url_path);"#,
+    "\n",
+);
+
+macro_rules! migrate_fn {
+    ($func:ident, $pool:ty, $dialect:ident, $create:expr, $insert:expr) => {
+        /// Apply the pending migrations in a transaction and return the schema version.
+        ///
+        /// This fails if the database was migrated by a newer binary.
+        pub(crate) async fn $func(pool: &$pool) -> Result<i64> {
+            let mut tx = pool.begin().await?;
+            //
+            sqlx::query($create).execute(&mut *tx).await?;
+            //
+            let current = sqlx::query(r#"SELECT COALESCE(MAX(version), 0) FROM schema_version"#)
+                .fetch_one(&mut *tx)
+                .await?
+                .get::<i64, _>(0);
+            let latest = MIGRATIONS.last().map(|m| m.version).unwrap_or(0);
+            if current > latest {
+                tx.rollback().await?;
+                return Err(anyhow::anyhow!(
+                    "the database schema version {current} is newer than this binary supports: {latest}"
+                ));
+            }
+            //
+            for m in MIGRATIONS.iter().filter(|m| m.version > current) {
+                dioxus_logger::tracing::info!("migrate: {}: {}", m.version, m.description);
+                sqlx::raw_sql(m.$dialect).execute(&mut *tx).await?;
+                sqlx::query($insert)
+                    .bind(m.version)
+                    .bind(m.description)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            //
+            tx.commit().await?;
+            Ok(latest)
+        }
+    };
+}
+
+migrate_fn!(
+    migrate,
+    sqlx::sqlite::SqlitePool,
+    sqlite,
+    concat!(
+        r#"CREATE TABLE IF NOT EXISTS schema_version ("#,
+        r#" version INTEGER PRIMARY KEY,"#,
+        r#" description TEXT NOT NULL,"#,
+        r#" applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP"#,
+        r#");"#,
+    ),
+    r#"INSERT INTO schema_version (version, description) VALUES (?, ?)"#
+);
+
+#[cfg(feature = "postgres")]
+migrate_fn!(
+    migrate_postgres,
+    sqlx::postgres::PgPool,
+    postgres,
+    concat!(
+        r#"CREATE TABLE IF NOT EXISTS schema_version ("#,
+        r#" version BIGINT PRIMARY KEY,"#,
+        r#" description TEXT NOT NULL,"#,
+        r#" applied_at TEXT NOT NULL DEFAULT "#,
+        r#"to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')"#,
+        r#");"#,
+    ),
+    r#"INSERT INTO schema_version (version, description) VALUES ($1, $2)"#
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latest() -> i64 {
+        MIGRATIONS.last().unwrap().version
+    }
+
+    async fn sqlite_pool() -> sqlx::sqlite::SqlitePool {
+        sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn versions_are_in_order() {
+        for (i, m) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(m.version, i as i64 + 1, "{}", m.description);
+        }
+    }
+
+    #[tokio::test]
+    async fn sqlite_from_empty_to_latest() {
+        let pool = sqlite_pool().await;
+        assert_eq!(migrate(&pool).await.unwrap(), latest());
+        let versions: Vec<i64> = sqlx::query_scalar(r#"SELECT version FROM schema_version"#)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(versions, (1..=latest()).collect::<Vec<_>>());
+        // The schema has the columns of the latest version
+        sqlx::query(r#"SELECT album, tags, deleted_at, image_hash, provider_id FROM Cat"#)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        // Again, nothing is applied and the rows are kept
+        sqlx::query(r#"INSERT INTO Bicmid (value) VALUES ('owner')"#)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(migrate(&pool).await.unwrap(), latest());
+        let count: i64 = sqlx::query_scalar(r#"SELECT count(*) FROM schema_version"#)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, latest());
+        let count: i64 = sqlx::query_scalar(r#"SELECT count(*) FROM Bicmid"#)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[tokio::test]
+    async fn sqlite_from_baseline_keeps_the_cats() {
+        use crate::backends::store_sqlite::SqliteCatStore;
+        use crate::backends::{CatCursor, CatStore};
+        //
+        let pool = sqlite_pool().await;
+        // The database of the baseline, that `create_tables()` made without `schema_version`,
+        // is the same as the version 1
+        sqlx::raw_sql(MIGRATIONS[0].sqlite)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let sql = concat!(
+            r#"INSERT INTO Bicmid (id, value) VALUES (1, 'legacy-bicmid');"#,
+            r#"INSERT INTO UrlOrigin (id, value) VALUES (1, 'https://cdn2.thecatapi.com');"#,
+            r#"INSERT INTO Cat (id, bicmid_id, create_at, url_origin_id, url_path) VALUES"#,
+            r#" (1, 1, '2025-01-01 00:00:00', 1, '/images/a.jpg'),"#,
+            r#" (2, 1, '2025-01-02 00:00:00', 1, '/images/b.jpg'),"#,
+            r#" (3, 1, '2025-01-03 00:00:00', 1, '/images/a.jpg');"#,
+        );
+        sqlx::raw_sql(sql).execute(&pool).await.unwrap();
+        assert_eq!(migrate(&pool).await.unwrap(), latest());
+        // The duplicated cat is removed by the version 2, the older one is kept
+        let store = SqliteCatStore::new(pool.clone());
+        let cats = store
+            .list_cats_page("legacy-bicmid", CatCursor::First, 10)
+            .await
+            .unwrap();
+        let cats: Vec<_> = cats
+            .iter()
+            .map(|cat| (cat.id, cat.url.as_str(), cat.create_at.as_str()))
+            .collect();
+        assert_eq!(
+            cats,
+            [
+                (
+                    2,
+                    "https://cdn2.thecatapi.com/images/b.jpg",
+                    "2025-01-02 00:00:00"
+                ),
+                (
+                    1,
+                    "https://cdn2.thecatapi.com/images/a.jpg",
+                    "2025-01-01 00:00:00"
+                ),
+            ]
+        );
+        // The new columns have the defaults
+        let (tags, album, deleted_at): (String, Option<String>, Option<String>) =
+            sqlx::query_as(r#"SELECT tags, album, deleted_at FROM Cat WHERE id = 1"#)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((tags.as_str(), album, deleted_at), ("[]", None, None));
+        // Again, nothing is applied
+        assert_eq!(migrate(&pool).await.unwrap(), latest());
+        assert_eq!(store.count_of_cats("legacy-bicmid").await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn sqlite_refuses_the_newer_database() {
+        let pool = sqlite_pool().await;
+        migrate(&pool).await.unwrap();
+        sqlx::query(r#"INSERT INTO schema_version (version, description) VALUES (?, 'newer')"#)
+            .bind(latest() + 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let e = migrate(&pool).await.unwrap_err();
+        assert!(e.to_string().contains("newer than this binary"), "{e}");
+    }
+
+    #[cfg(feature = "postgres")]
+    #[tokio::test]
+    async fn postgres_from_empty_to_latest() {
+        let name = "migrate_from_empty_to_latest";
+        let Some(pool) = crate::backends::store_tests::postgres_test_pool(name).await else {
+            return;
+        };
+        assert_eq!(migrate_postgres(&pool).await.unwrap(), latest());
+        sqlx::query(r#"SELECT album, tags, deleted_at, image_hash, provider_id FROM Cat"#)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(migrate_postgres(&pool).await.unwrap(), latest());
+        let count: i64 = sqlx::query_scalar(r#"SELECT count(*) FROM schema_version"#)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, latest());
+        // The newer database is refused
+        sqlx::query(r#"INSERT INTO schema_version (version, description) VALUES ($1, 'newer')"#)
+            .bind(latest() + 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(migrate_postgres(&pool).await.is_err());
+    }
+}
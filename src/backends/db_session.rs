@@ -5,16 +5,141 @@ use anyhow::Result;
 use std::path::PathBuf;
 
 #[cfg(feature = "server")]
-pub async fn session_store() -> Result<tower_sessions_sqlx_store::SqliteStore> {
-    use tower_sessions_sqlx_store::SqliteStore;
+use tower_sessions::session::{Id, Record};
+
+#[cfg(feature = "server")]
+use tower_sessions::session_store;
+
+/// the session store on the same kind of database as the cat store
+#[cfg(feature = "server")]
+#[derive(Debug, Clone)]
+pub enum AnySessionStore {
+    /// the store, and the pool of it for the maintenance
+    Sqlite(
+        tower_sessions_sqlx_store::SqliteStore,
+        sqlx::sqlite::SqlitePool,
+    ),
+    #[cfg(feature = "postgres")]
+    Postgres(tower_sessions_sqlx_store::PostgresStore),
+}
+
+#[cfg(feature = "server")]
+#[async_trait::async_trait]
+impl session_store::SessionStore for AnySessionStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        match self {
+            Self::Sqlite(store, _) => store.create(record).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(store) => store.create(record).await,
+        }
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        match self {
+            Self::Sqlite(store, _) => store.save(record).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(store) => store.save(record).await,
+        }
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        match self {
+            Self::Sqlite(store, _) => store.load(session_id).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(store) => store.load(session_id).await,
+        }
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        match self {
+            Self::Sqlite(store, _) => store.delete(session_id).await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(store) => store.delete(session_id).await,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+#[async_trait::async_trait]
+impl session_store::ExpiredDeletion for AnySessionStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        match self {
+            Self::Sqlite(store, _) => store.delete_expired().await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(store) => store.delete_expired().await,
+        }
+    }
+}
 
+#[cfg(feature = "server")]
+impl AnySessionStore {
+    /// Reclaim the free space of the database, as needed
+    pub async fn optimize(&self) -> Result<()> {
+        match self {
+            Self::Sqlite(_, pool) => {
+                super::optimize_sqlite(pool).await?;
+            }
+            // The dead rows are reclaimed by the autovacuum of the server
+            #[cfg(feature = "postgres")]
+            Self::Postgres(_) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Open the session store on the same database as the cat store
+///
+/// This fails on the database url, that the cat store refuses, too.
+#[cfg(feature = "server")]
+pub async fn session_store() -> Result<AnySessionStore> {
+    if let Some(db_url) = super::database_url() {
+        if super::is_postgres_url(&db_url) {
+            #[cfg(feature = "postgres")]
+            return Ok(AnySessionStore::Postgres(
+                postgres_session_store(&db_url).await?,
+            ));
+            #[cfg(not(feature = "postgres"))]
+            return Err(anyhow::anyhow!(
+                "this binary is built without the `postgres` feature"
+            ));
+        }
+        return Err(anyhow::anyhow!("unsupported database url: '{db_url}'"));
+    }
     let pool = create_sqlx_pool().await?;
+    let store = sqlite_session_store(pool.clone()).await?;
+    Ok(AnySessionStore::Sqlite(store, pool))
+}
+
+#[cfg(feature = "server")]
+async fn sqlite_session_store(
+    pool: sqlx::sqlite::SqlitePool,
+) -> Result<tower_sessions_sqlx_store::SqliteStore> {
+    use tower_sessions_sqlx_store::SqliteStore;
+
     let session_store = SqliteStore::new(pool).with_table_name("sessions").unwrap();
     // create table if not exists
     session_store.migrate().await?;
     Ok(session_store)
 }
 
+#[cfg(feature = "postgres")]
+#[cfg(feature = "server")]
+async fn postgres_session_store(db_url: &str) -> Result<tower_sessions_sqlx_store::PostgresStore> {
+    use sqlx::postgres::PgPoolOptions;
+    use tower_sessions_sqlx_store::PostgresStore;
+
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(db_url)
+        .await?;
+    let session_store = PostgresStore::new(pool)
+        .with_table_name("sessions")
+        .unwrap();
+    // create schema and table if not exists
+    session_store.migrate().await?;
+    Ok(session_store)
+}
+
 #[cfg(feature = "server")]
 async fn create_sqlx_pool() -> Result<sqlx::sqlite::SqlitePool> {
     use sqlx::sqlite::SqliteConnectOptions;
@@ -35,12 +160,8 @@ async fn create_sqlx_pool() -> Result<sqlx::sqlite::SqlitePool> {
 
 #[cfg(feature = "server")]
 fn get_db_path_() -> PathBuf {
-    let key1 = "CATTONGUE_DB_SESSION_PATH";
-    if let Ok(s) = std::env::var(key1) {
-        return PathBuf::from(s);
-    }
-    let mut data_dir = super::data_base_dir();
-    let db_file = "sessions.sqlite3".to_string();
-    data_dir.push(db_file);
-    data_dir
+    match &super::config().database.session_path {
+        Some(path) => path.clone(),
+        None => super::data_base_dir().join("sessions.sqlite3"),
+    }
 }
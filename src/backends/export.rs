@@ -0,0 +1,436 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+use dioxus_fullstack::FileStream;
+
+#[cfg(feature = "server")]
+use super::{
+    check_bulk_ids, fetch_image, get_owner_from_session, image_content_type, read_cached_image,
+    read_local_cat_image, store, CatRecord, CatStore,
+};
+
+#[cfg(feature = "server")]
+use futures::{Stream, StreamExt, TryStreamExt};
+
+/// the export formats: (`format` parameter, label)
+pub const EXPORT_FORMATS: &[(&str, &str)] = &[
+    ("jsonl", "JSON Lines"),
+    ("csv", "CSV"),
+    ("zip", "ZIP with images"),
+];
+
+/// Return the url of `export_cats()`, for a download link
+///
+/// The link is only for the web, the webview of the app has not the session cookie.
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+pub fn export_url(format: &str) -> String {
+    format!(
+        "{}/api/v1/export?format={format}",
+        dioxus_fullstack::get_server_url()
+    )
+}
+
+/// Download the file of `format` of every cat by `export_cats()`, and return the path of it
+///
+/// The app calls the server function with the session, and saves the file
+/// in the download directory.
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+pub async fn download_cats(format: &str) -> Result<String> {
+    let file = export_cats(format.to_string()).await?;
+    save_file_stream(file).await
+}
+
+/// Save the file of the response in the download directory, and return the path of it
+///
+/// The file is written as `*.part`, then renamed not to leave the broken one.
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+async fn save_file_stream(mut file: FileStream) -> Result<String> {
+    use futures::StreamExt;
+    use std::io::Write;
+    //
+    let Some(dir) = dirs::download_dir().or_else(crate::settings::app_data_dir) else {
+        return Err(anyhow::anyhow!("could NOT get the download directory"));
+    };
+    std::fs::create_dir_all(&dir)?;
+    // The name is of the server, so only the last component of it
+    let name = std::path::Path::new(file.file_name())
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("cattongue")
+        .to_string();
+    let path = free_path(&dir, &name);
+    let part = dir.join(format!("{name}.part"));
+    let r = async {
+        let mut out = std::fs::File::create(&part)?;
+        while let Some(chunk) = file.next().await {
+            out.write_all(&chunk?)?;
+        }
+        out.flush()?;
+        std::fs::rename(&part, &path)?;
+        Ok(path.display().to_string())
+    }
+    .await;
+    if r.is_err() {
+        let _ = std::fs::remove_file(&part);
+    }
+    r
+}
+
+/// Return the path of `name` in `dir`, that is `name (1)` etc. if it exists
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+fn free_path(dir: &std::path::Path, name: &str) -> std::path::PathBuf {
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{ext}")),
+        _ => (name, String::new()),
+    };
+    let mut path = dir.join(name);
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{stem} ({n}){ext}"));
+        n += 1;
+    }
+    path
+}
+
+/// Download the file of `format` of the cats of `ids` by `export_selected_cats()`,
+/// and return the path of it
+///
+/// The app calls the server function with the session, and saves the file
+/// in the download directory.
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+pub async fn download_selected_cats(format: &str, ids: &[i64]) -> Result<Option<String>> {
+    let file = export_selected_cats(format.to_string(), ids.to_vec()).await?;
+    save_file_stream(file).await.map(Some)
+}
+
+/// Download the file of `format` of the cats of `ids`, by `export_selected_cats()`
+///
+/// The ids are too many for a link, so the browser posts them and saves the response,
+/// then the path of it is unknown.
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+pub async fn download_selected_cats(format: &str, ids: &[i64]) -> Result<Option<String>> {
+    let js = concat!(
+        r#"const [url, format, ids] = await dioxus.recv();"#,
+        r#"const res = await fetch(url, {"#,
+        r#" method: 'POST', credentials: 'include',"#,
+        r#" headers: { 'Content-Type': 'application/json' },"#,
+        r#" body: JSON.stringify({ format, ids }),"#,
+        r#"});"#,
+        r#"if (!res.ok) { return `${res.status}: ${await res.text()}`; }"#,
+        r#"const disposition = res.headers.get('Content-Disposition') ?? '';"#,
+        r#"const name = disposition.match(/filename="([^"]+)"/)?.[1] ?? 'cattongue';"#,
+        r#"const href = URL.createObjectURL(await res.blob());"#,
+        r#"const a = document.createElement('a');"#,
+        r#"a.href = href; a.download = name;"#,
+        r#"document.body.appendChild(a); a.click(); a.remove();"#,
+        r#"setTimeout(() => URL.revokeObjectURL(href), 60000);"#,
+        r#"return '';"#
+    );
+    let url = format!(
+        "{}/api/v1/export/selected",
+        dioxus_fullstack::get_server_url()
+    );
+    let eval = document::eval(js);
+    eval.send((url, format, ids))?;
+    let e = eval.join::<String>().await?;
+    if !e.is_empty() {
+        return Err(anyhow::anyhow!("export: {e}"));
+    }
+    Ok(None)
+}
+
+/// Stream every cat of the session as a file of `format`
+///
+/// `format` is one of `EXPORT_FORMATS`.
+#[get("/api/v1/export?format", session: tower_sessions::Session)]
+pub async fn export_cats(format: String) -> Result<FileStream> {
+    let owner = get_owner_from_session(&session).await?;
+    export_file(&format, cat_records(store(), owner))
+}
+
+/// Return the cats of `ids` of the session as a file of `format`
+///
+/// `format` is one of `EXPORT_FORMATS`.
+#[post("/api/v1/export/selected", session: tower_sessions::Session)]
+pub async fn export_selected_cats(format: String, ids: Vec<i64>) -> Result<FileStream> {
+    let owner = get_owner_from_session(&session).await?;
+    check_bulk_ids(&ids)?;
+    let cats = store().select_cats(&owner, &ids).await?;
+    export_file(&format, futures::stream::iter(cats.into_iter().map(Ok)))
+}
+
+/// Return the file of `format` of the stream of cats
+#[cfg(feature = "server")]
+fn export_file(
+    format: &str,
+    cats: impl Stream<Item = Result<CatRecord>> + Send + 'static,
+) -> Result<FileStream> {
+    use dioxus_fullstack::body::Body;
+    //
+    let (name, content_type, body) = match format {
+        "jsonl" => {
+            let lines = cats.and_then(|cat| async move {
+                let mut line = serde_json::to_string(&cat)?;
+                line.push('\n');
+                Ok(line)
+            });
+            (
+                "cattongue.jsonl",
+                "application/jsonl",
+                Body::from_stream(lines),
+            )
+        }
+        "csv" => {
+            let header = futures::stream::once(async { Ok(CSV_HEADER.to_string()) });
+            let lines = cats.map_ok(|cat| csv_line(&cat));
+            let lines = header.chain(lines);
+            ("cattongue.csv", "text/csv", Body::from_stream(lines))
+        }
+        "zip" => ("cattongue.zip", "application/zip", zip_body(cats)),
+        _ => return Err(anyhow::anyhow!("unknown export format: '{format}'")),
+    };
+    Ok(FileStream::from_raw(
+        name.to_string(),
+        None,
+        content_type.to_string(),
+        body.into_data_stream(),
+    ))
+}
+
+// The cats are read page by page, so that large collections don't have to fit in memory
+#[cfg(feature = "server")]
+const EXPORT_PAGE_SIZE: i64 = 500;
+
+#[cfg(feature = "server")]
+const CSV_HEADER: &str = "id,url,create_at\n";
+
+/// Return the stream of every cat of `owner` in `store`, in ascending order of id
+#[cfg(feature = "server")]
+fn cat_records(
+    store: &'static dyn CatStore,
+    owner: String,
+) -> impl Stream<Item = Result<CatRecord>> + Send + 'static {
+    futures::stream::try_unfold(Some(0i64), move |after_id| {
+        let owner = owner.clone();
+        async move {
+            let Some(after_id) = after_id else {
+                return Ok::<_, anyhow::Error>(None);
+            };
+            let cats = store
+                .export_cats(&owner, after_id, EXPORT_PAGE_SIZE)
+                .await?;
+            if cats.is_empty() {
+                return Ok(None);
+            }
+            let next = if (cats.len() as i64) < EXPORT_PAGE_SIZE {
+                None
+            } else {
+                cats.last().map(|cat| cat.id)
+            };
+            Ok(Some((cats, next)))
+        }
+    })
+    .map_ok(|cats| futures::stream::iter(cats.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+#[cfg(feature = "server")]
+fn csv_line(cat: &CatRecord) -> String {
+    format!(
+        "{},{},{}\n",
+        cat.id,
+        csv_field(&cat.url),
+        csv_field(&cat.create_at)
+    )
+}
+
+/// Quote the field, if it has a special character of csv
+#[cfg(feature = "server")]
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Return the body of the zip file, that is written by a spawned task
+#[cfg(feature = "server")]
+fn zip_body(
+    cats: impl Stream<Item = Result<CatRecord>> + Send + 'static,
+) -> dioxus_fullstack::body::Body {
+    let (writer, reader) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        if let Err(e) = write_zip(cats, writer).await {
+            dioxus_logger::tracing::error!("export zip: {e}");
+        }
+    });
+    dioxus_fullstack::body::Body::from_stream(tokio_util::io::ReaderStream::new(reader))
+}
+
+/// Write `cats.jsonl` and the image of each cat as `images/{id}.{ext}`
+#[cfg(feature = "server")]
+async fn write_zip(
+    cats: impl Stream<Item = Result<CatRecord>> + Send,
+    writer: tokio::io::DuplexStream,
+) -> Result<()> {
+    use async_zip::tokio::write::ZipFileWriter;
+    use async_zip::{Compression, ZipEntryBuilder};
+    //
+    // The entries are stored without compression, the images are already compressed
+    let mut zip = ZipFileWriter::with_tokio(writer);
+    let mut index = String::new();
+    let mut cats = std::pin::pin!(cats);
+    while let Some(cat) = cats.next().await {
+        let cat = cat?;
+        index.push_str(&serde_json::to_string(&cat)?);
+        index.push('\n');
+        let bytes = match export_image(&cat).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                dioxus_logger::tracing::warn!("export zip: skip image '{}': {e}", cat.url);
+                continue;
+            }
+        };
+        let Some(content_type) = image_content_type(&bytes) else {
+            dioxus_logger::tracing::warn!("export zip: skip image '{}': not an image", cat.url);
+            continue;
+        };
+        let name = format!("images/{}.{}", cat.id, image_ext(content_type));
+        let entry = ZipEntryBuilder::new(name.into(), Compression::Stored);
+        zip.write_entry_whole(entry, &bytes).await?;
+    }
+    let entry = ZipEntryBuilder::new("cats.jsonl".into(), Compression::Stored);
+    zip.write_entry_whole(entry, index.as_bytes()).await?;
+    zip.close().await?;
+    Ok(())
+}
+
+/// Return the image of the cat
+///
+/// The cached image and the image of the `local` provider are read on this server.
+#[cfg(feature = "server")]
+async fn export_image(cat: &CatRecord) -> Result<Vec<u8>> {
+    if let Some(hash) = &cat.image_hash {
+        if let Some(bytes) = read_cached_image(hash).await? {
+            return Ok(bytes);
+        }
+    }
+    if cat.url.starts_with('/') {
+        return read_local_cat_image(&cat.url).await;
+    }
+    fetch_image(&cat.url).await
+}
+
+/// Return the extension of the image file of `content_type`
+#[cfg(feature = "server")]
+fn image_ext(content_type: &str) -> &str {
+    match content_type {
+        "image/jpeg" => "jpg",
+        _ => content_type.trim_start_matches("image/"),
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "server")]
+mod tests {
+    use super::*;
+    use crate::backends::store_memory::MemoryCatStore;
+    use crate::backends::NewCat;
+
+    fn record(id: i64, url: &str) -> CatRecord {
+        CatRecord {
+            id,
+            url: url.to_string(),
+            create_at: "2026-01-01 00:00:00".to_string(),
+            image_hash: None,
+        }
+    }
+
+    // The text of the export file of `format` of `cats`
+    async fn export_text(format: &str, cats: Vec<CatRecord>) -> String {
+        let file = export_file(format, futures::stream::iter(cats.into_iter().map(Ok))).unwrap();
+        let chunks: Vec<_> = file.try_collect().await.unwrap();
+        String::from_utf8(chunks.concat()).unwrap()
+    }
+
+    #[test]
+    fn csv_field_is_quoted() {
+        assert_eq!(
+            csv_field("https://example.com/a.png"),
+            "https://example.com/a.png"
+        );
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("a\"b"), "\"a\"\"b\"");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_field("a\r\nb"), "\"a\r\nb\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn csv_line_is_of_the_header() {
+        let line = csv_line(&record(7, "https://example.com/a,b.png"));
+        assert_eq!(
+            line,
+            "7,\"https://example.com/a,b.png\",2026-01-01 00:00:00\n"
+        );
+        assert_eq!(CSV_HEADER.trim_end().split(',').count(), 3);
+    }
+
+    #[tokio::test]
+    async fn cat_records_are_read_over_the_pages() {
+        let store: &'static MemoryCatStore = Box::leak(Box::new(MemoryCatStore::new()));
+        let count = EXPORT_PAGE_SIZE as usize * 2 + 1;
+        for i in 0..count {
+            let cat = NewCat::from_url(&format!("https://example.com/{i}.png"));
+            store.save_cat("owner", &cat).await.unwrap();
+        }
+        store
+            .save_cat("other", &NewCat::from_url("https://example.com/x.png"))
+            .await
+            .unwrap();
+        let cats: Vec<CatRecord> = cat_records(store, "owner".to_string())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(cats.len(), count);
+        assert!(cats.windows(2).all(|w| w[0].id < w[1].id));
+        assert_eq!(
+            cats[count - 1].url,
+            format!("https://example.com/{}.png", count - 1)
+        );
+        // The page of just the page size is followed by the empty one
+        let store: &'static MemoryCatStore = Box::leak(Box::new(MemoryCatStore::new()));
+        for i in 0..EXPORT_PAGE_SIZE {
+            let cat = NewCat::from_url(&format!("https://example.com/{i}.png"));
+            store.save_cat("owner", &cat).await.unwrap();
+        }
+        let cats: Vec<CatRecord> = cat_records(store, "owner".to_string())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(cats.len(), EXPORT_PAGE_SIZE as usize);
+    }
+
+    #[tokio::test]
+    async fn export_is_imported_back() {
+        let cats = vec![
+            record(1, "https://example.com/a.png"),
+            record(2, "https://example.com/b,c.png"),
+            record(3, "https://example.com/\"d\".png"),
+            record(4, "/api/v1/local_cats/e f.png"),
+        ];
+        let urls: Vec<Option<String>> = cats.iter().map(|cat| Some(cat.url.clone())).collect();
+        for (format, name) in [("jsonl", "cattongue.jsonl"), ("csv", "cattongue.csv")] {
+            let text = export_text(format, cats.clone()).await;
+            let rows = crate::backends::import::parse_import_text(name, &text).unwrap();
+            assert_eq!(rows, urls, "{format}");
+        }
+        // The empty export is imported as nothing
+        for (format, name) in [("jsonl", "cattongue.jsonl"), ("csv", "cattongue.csv")] {
+            let text = export_text(format, Vec::new()).await;
+            let rows = crate::backends::import::parse_import_text(name, &text).unwrap();
+            assert!(rows.is_empty(), "{format}");
+        }
+        assert!(export_file("xml", futures::stream::empty()).is_err());
+    }
+}
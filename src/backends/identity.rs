@@ -0,0 +1,132 @@
+use anyhow::Result;
+use dioxus::fullstack::Lazy;
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha512};
+
+// The prefix of the identity, that is never a bicmid of browserinfocm
+const IDENTITY_PREFIX: &str = "u-";
+
+// The secret key to sign the identity tokens, that the key of the cookies is derived from
+static SECRET: Lazy<Vec<u8>> = Lazy::lazy();
+
+/// Load the secret key of the signed or private cookies.
+///
+/// The key is `identity.secret` of the configuration, or the random key
+/// that is created in `identity.key` of the data directory at the first time.
+pub async fn init_identity() -> Result<()> {
+    let secret = match &super::config().identity.secret {
+        Some(s) => s.clone().into_bytes(),
+        None => load_or_create_key()?,
+    };
+    // The router may be rebuilt by hot-reloading, then keep the first key
+    let _ = SECRET.try_set(secret);
+    Ok(())
+}
+
+fn load_or_create_key() -> Result<Vec<u8>> {
+    let mut path = super::data_base_dir();
+    path.push("identity.key");
+    match std::fs::read_to_string(&path) {
+        Ok(s) => return from_hex(s.trim()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    let key: [u8; 32] = rand::random();
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut opts, 0o600);
+    std::io::Write::write_all(&mut opts.open(&path)?, to_hex(&key).as_bytes())?;
+    dioxus_logger::tracing::info!("created the identity key: '{}'", path.display());
+    Ok(key.to_vec())
+}
+
+/// Return a new random identity of an owner of cats
+pub(crate) fn new_identity() -> String {
+    let id: [u8; 16] = rand::random();
+    format!("{IDENTITY_PREFIX}{}", to_hex(&id))
+}
+
+/// Return a new random id of the device session, that is kept over the session cookie
+pub(crate) fn new_sid() -> String {
+    let id: [u8; 16] = rand::random();
+    to_hex(&id)
+}
+
+/// Return true, if `bicmid` is a bicmid of the time before the identity,
+/// that owns the cats directly
+pub(crate) fn is_legacy_bicmid(bicmid: &str) -> bool {
+    !bicmid.is_empty() && !bicmid.starts_with(IDENTITY_PREFIX)
+}
+
+/// Return the token of `identity` on the device session `sid`,
+/// that is `{identity}.{sid}.{signature}`
+///
+/// The client keeps the token over the expiry of the session cookie
+/// and the restart of the app, so the identity is restored by it.
+pub(crate) fn issue_token(identity: &str, sid: &str) -> String {
+    let payload = format!("{identity}.{sid}");
+    let sig = mac().chain_update(payload.as_bytes()).finalize();
+    format!("{payload}.{}", to_hex(&sig.into_bytes()))
+}
+
+/// Return the identity and the device session of `token`, if the signature is valid
+pub(crate) fn verify_token(token: &str) -> Option<(String, String)> {
+    let (payload, sig) = token.rsplit_once('.')?;
+    let (identity, sid) = payload.split_once('.')?;
+    if !identity.starts_with(IDENTITY_PREFIX) || sid.is_empty() {
+        return None;
+    }
+    let sig = from_hex(sig).ok()?;
+    let mac = mac().chain_update(payload.as_bytes());
+    // `verify_slice()` compares in constant time
+    mac.verify_slice(&sig).ok()?;
+    Some((identity.to_string(), sid.to_string()))
+}
+
+/// Return the current unix time in seconds
+pub(crate) fn unix_now() -> i64 {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+    now.map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+/// Return the key of the signed or private cookies, that is derived from the secret key
+pub(crate) fn cookie_key() -> Vec<u8> {
+    let mac = Hmac::<Sha512>::new_from_slice(SECRET.get()).expect("HMAC takes a key of any size");
+    let key = mac.chain_update(b"cattongue cookie key").finalize();
+    key.into_bytes().to_vec()
+}
+
+/// Return the key of the owner, that the client keeps the changes of the owner by
+///
+/// It is derived from the secret key, so the identity is not shown to the client.
+pub(crate) fn owner_key(owner: &str) -> String {
+    let mac = Hmac::<Sha512>::new_from_slice(SECRET.get()).expect("HMAC takes a key of any size");
+    let key = mac
+        .chain_update(b"cattongue owner key")
+        .chain_update(owner.as_bytes())
+        .finalize();
+    to_hex(&key.into_bytes()[..16])
+}
+
+fn mac() -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(SECRET.get()).expect("HMAC takes a key of any size")
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return Err(anyhow::anyhow!("invalid hex string"));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&s[i..i + 2], 16)?))
+        .collect()
+}
@@ -0,0 +1,263 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+use dioxus_fullstack::response::Response;
+
+#[cfg(feature = "server")]
+use super::{fetch_image, store};
+
+#[cfg(feature = "server")]
+use dioxus_fullstack::{body::Body, http::header, StatusCode};
+
+#[cfg(feature = "server")]
+use std::path::PathBuf;
+
+// The cached image never changes, because the name is the hash of it
+#[cfg(feature = "server")]
+const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Return the url of the cached image of `hash`
+///
+/// The url is relative to this server.
+#[cfg(feature = "server")]
+pub(crate) fn image_url(hash: &str) -> String {
+    format!("/api/v1/images/{hash}")
+}
+
+/// the widths of the thumbnails of the cached image
+pub const THUMB_WIDTHS: &[u32] = &[160, 320, 640];
+
+/// Return the url of the thumbnail of `hash`, for the `img` element
+pub fn thumb_url(hash: &str, width: u32) -> String {
+    format!(
+        "{}/api/v1/thumbs/{hash}/{width}",
+        dioxus_fullstack::get_server_url()
+    )
+}
+
+/// Return the `srcset` of the thumbnails of `hash`
+pub fn thumb_srcset(hash: &str) -> String {
+    THUMB_WIDTHS
+        .iter()
+        .map(|w| format!("{} {w}w", thumb_url(hash, *w)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Prepare the image cache, if it is enabled.
+///
+/// The image cache is enabled by `image_cache.enabled` of the configuration,
+/// then the saved images are downloaded into the directory of
+/// `image_cache.dir`, and the favorites are shown from it.
+#[cfg(feature = "server")]
+pub async fn init_image_cache() -> Result<()> {
+    if !is_image_cache_enabled() {
+        return Ok(());
+    }
+    let dir = image_cache_dir();
+    tokio::fs::create_dir_all(&dir).await?;
+    dioxus_logger::tracing::info!("image cache: '{}'", dir.display());
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+fn is_image_cache_enabled() -> bool {
+    super::config().image_cache.enabled
+}
+
+#[cfg(feature = "server")]
+fn image_cache_dir() -> PathBuf {
+    match &super::config().image_cache.dir {
+        Some(dir) => dir.clone(),
+        None => super::data_base_dir().join("images"),
+    }
+}
+
+/// Download the image of `url` into the image cache, by a spawned task
+///
+/// The image that is already on this server is not cached.
+#[cfg(feature = "server")]
+pub(crate) fn cache_image_later(url: String) {
+    if !is_image_cache_enabled() || url.starts_with('/') {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = cache_image(&url).await {
+            dioxus_logger::tracing::info!("image cache: '{url}': {e}");
+        }
+    });
+}
+
+#[cfg(feature = "server")]
+async fn cache_image(url: &str) -> Result<()> {
+    let bytes = fetch_image(url).await?;
+    let hash = sha256_hex(&bytes);
+    let path = image_cache_dir().join(&hash);
+    if !tokio::fs::try_exists(&path).await? {
+        // The other request may read it while writing, so write and rename
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, &bytes).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+    }
+    store().set_image_hash(url, &hash).await?;
+    for width in THUMB_WIDTHS {
+        thumbnail(&hash, *width).await?;
+    }
+    Ok(())
+}
+
+/// Return the cached image of `hash`
+///
+/// Return `None`, if the image is not cached.
+#[cfg(feature = "server")]
+pub(crate) async fn read_cached_image(hash: &str) -> Result<Option<Vec<u8>>> {
+    if !is_image_hash(hash) {
+        return Ok(None);
+    }
+    match tokio::fs::read(image_cache_dir().join(hash)).await {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Return the thumbnail of the cached image, that is made at the first time
+///
+/// Return `None`, if the image is not cached.
+#[cfg(feature = "server")]
+async fn thumbnail(hash: &str, width: u32) -> Result<Option<Vec<u8>>> {
+    let mut path = image_cache_dir();
+    path.push("thumbs");
+    path.push(format!("{hash}-{width}"));
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => return Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    let Some(bytes) = read_cached_image(hash).await? else {
+        return Ok(None);
+    };
+    // Resizing is cpu bound, so it does not block the async runtime
+    let bytes = tokio::task::spawn_blocking(move || make_thumbnail(&bytes, width)).await??;
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, &bytes).await?;
+    tokio::fs::rename(&tmp_path, &path).await?;
+    Ok(Some(bytes))
+}
+
+/// Return the jpeg of the image that is resized to `width`
+///
+/// The smaller image is not enlarged.
+#[cfg(feature = "server")]
+fn make_thumbnail(bytes: &[u8], width: u32) -> Result<Vec<u8>> {
+    let img = image::load_from_memory(bytes)?;
+    let img = if img.width() > width {
+        img.resize(width, u32::MAX, image::imageops::FilterType::Triangle)
+    } else {
+        img
+    };
+    let mut out = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgb8(img.to_rgb8()).write_to(&mut out, image::ImageFormat::Jpeg)?;
+    Ok(out.into_inner())
+}
+
+#[cfg(feature = "server")]
+fn sha256_hex(bytes: &[u8]) -> String {
+    use sha2::Digest;
+    use std::fmt::Write;
+    let digest = sha2::Sha256::digest(bytes);
+    digest.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+#[cfg(feature = "server")]
+fn is_image_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Return the content type of the image by the magic number
+#[cfg(feature = "server")]
+pub(crate) fn image_content_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// Return the cached image
+///
+/// The response can be cached forever, and is validated by the etag.
+#[get("/api/v1/images/{hash}", headers: dioxus_fullstack::HeaderMap)]
+pub async fn cached_image(hash: String) -> Result<Response> {
+    if !is_image_hash(&hash) {
+        return status_response(StatusCode::NOT_FOUND);
+    }
+    let etag = format!("\"{hash}\"");
+    if is_not_modified(&headers, &etag) {
+        return image_response(StatusCode::NOT_MODIFIED, &etag, None, Body::empty());
+    }
+    let Some(bytes) = read_cached_image(&hash).await? else {
+        return status_response(StatusCode::NOT_FOUND);
+    };
+    let content_type = image_content_type(&bytes).unwrap_or("application/octet-stream");
+    image_response(StatusCode::OK, &etag, Some(content_type), Body::from(bytes))
+}
+
+/// Return the thumbnail of the cached image
+///
+/// `width` is one of `THUMB_WIDTHS`.
+#[get("/api/v1/thumbs/{hash}/{width}", headers: dioxus_fullstack::HeaderMap)]
+pub async fn cached_thumb(hash: String, width: u32) -> Result<Response> {
+    if !is_image_hash(&hash) || !THUMB_WIDTHS.contains(&width) {
+        return status_response(StatusCode::NOT_FOUND);
+    }
+    let etag = format!("\"{hash}-{width}\"");
+    if is_not_modified(&headers, &etag) {
+        return image_response(StatusCode::NOT_MODIFIED, &etag, None, Body::empty());
+    }
+    match thumbnail(&hash, width).await? {
+        Some(bytes) => image_response(StatusCode::OK, &etag, Some("image/jpeg"), Body::from(bytes)),
+        None => status_response(StatusCode::NOT_FOUND),
+    }
+}
+
+#[cfg(feature = "server")]
+fn is_not_modified(headers: &dioxus_fullstack::HeaderMap, etag: &str) -> bool {
+    match headers.get(header::IF_NONE_MATCH) {
+        Some(v) => v.as_bytes() == etag.as_bytes(),
+        None => false,
+    }
+}
+
+#[cfg(feature = "server")]
+fn image_response(
+    status: StatusCode,
+    etag: &str,
+    content_type: Option<&str>,
+    body: Body,
+) -> Result<Response> {
+    let mut builder = Response::builder()
+        .status(status)
+        .header(header::CACHE_CONTROL, CACHE_CONTROL)
+        .header(header::ETAG, etag);
+    if let Some(content_type) = content_type {
+        builder = builder.header(header::CONTENT_TYPE, content_type);
+    }
+    Ok(builder.body(body)?)
+}
+
+#[cfg(feature = "server")]
+fn status_response(status: StatusCode) -> Result<Response> {
+    Ok(Response::builder().status(status).body(Body::empty())?)
+}
@@ -0,0 +1,241 @@
+//! the download of the images of the urls that the users give
+//!
+//! The url is given by the user, so the host of it must be on the internet,
+//! not this server or the private network of it.
+
+use anyhow::Result;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use super::image_content_type;
+
+/// the largest image that is downloaded
+pub(crate) const MAX_IMAGE_SIZE: usize = 16 * 1024 * 1024;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REDIRECTS: usize = 5;
+
+/// Download the image of `url`
+///
+/// The url is refused, if the host of it is not public, e.g. `localhost`.
+/// The response is refused by the `Content-Type` or `Content-Length` before
+/// reading the body, and the body is read up to `MAX_IMAGE_SIZE`.
+pub(crate) async fn fetch_image(url: &str) -> Result<Vec<u8>> {
+    let url = reqwest::Url::parse(url)?;
+    check_url(&url)?;
+    let mut resp = client()?.get(url).send().await?.error_for_status()?;
+    if let Some(value) = resp.headers().get(reqwest::header::CONTENT_TYPE) {
+        let content_type = value.to_str().unwrap_or("").trim().to_ascii_lowercase();
+        if !content_type.starts_with("image/") {
+            return Err(anyhow::anyhow!("not an image: '{content_type}'"));
+        }
+    }
+    if let Some(len) = resp.content_length() {
+        if len > MAX_IMAGE_SIZE as u64 {
+            return Err(anyhow::anyhow!("too large image: {len} bytes"));
+        }
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        if bytes.len() + chunk.len() > MAX_IMAGE_SIZE {
+            return Err(anyhow::anyhow!(
+                "too large image: over {MAX_IMAGE_SIZE} bytes"
+            ));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    if image_content_type(&bytes).is_none() {
+        return Err(anyhow::anyhow!("not an image"));
+    }
+    Ok(bytes)
+}
+
+fn client() -> Result<reqwest::Client> {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    if let Some(client) = CLIENT.get() {
+        return Ok(client.clone());
+    }
+    let client = reqwest::Client::builder()
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(reqwest::redirect::Policy::custom(check_redirect))
+        // The proxy would resolve the host instead of `PublicResolver`
+        .no_proxy()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(FETCH_TIMEOUT)
+        .build()?;
+    Ok(CLIENT.get_or_init(|| client).clone())
+}
+
+/// Check the scheme and the host of `url`
+///
+/// The host name is checked by `PublicResolver`, because it is not resolved yet.
+fn check_url(url: &reqwest::Url) -> Result<()> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow::anyhow!("not a http url: '{url}'"));
+    }
+    let Some(host) = url.host_str() else {
+        return Err(anyhow::anyhow!("no host in the url: '{url}'"));
+    };
+    // The address in the url is connected without resolving
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        if !is_public_ip(ip) {
+            return Err(anyhow::anyhow!("not a public host: '{host}'"));
+        }
+    }
+    Ok(())
+}
+
+fn check_redirect(attempt: reqwest::redirect::Attempt) -> reqwest::redirect::Action {
+    if attempt.previous().len() >= MAX_REDIRECTS {
+        return attempt.error(anyhow::anyhow!("too many redirects"));
+    }
+    match check_url(attempt.url()) {
+        Ok(()) => attempt.follow(),
+        Err(e) => attempt.error(e),
+    }
+}
+
+/// the resolver that refuses the host of a non-public address
+///
+/// The connection uses the checked addresses, so the host can not be
+/// resolved to the other address after the check.
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+                return Err(format!("not a public host: '{host}' ({})", addr.ip()).into());
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Return `true`, if `ip` is on the internet
+///
+/// e.g. the loopback, private, link-local and reserved addresses are not.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match embedded_ipv4(ip) {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "this network"
+        || a == 0
+        // the shared address space of the carrier-grade nat
+        || (a == 100 && (b & 0xc0) == 64)
+        // the protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // the benchmarking
+        || (a == 198 && (b & 0xfe) == 18)
+        // the reserved
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let [s0, s1, ..] = ip.segments();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // the unique local
+        || (s0 & 0xfe00) == 0xfc00
+        // the link local, and the deprecated site local
+        || (s0 & 0xff80) == 0xfe80
+        // the documentation
+        || (s0 == 0x2001 && s1 == 0x0db8))
+}
+
+/// Return the ipv4 address in the ipv4-mapped, ipv4-compatible or nat64 address
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let s = ip.segments();
+    let is_embedded = s[..5] == [0; 5] && (s[5] == 0xffff || (s[5] == 0 && !ip.is_loopback()))
+        || s[..6] == [0x64, 0xff9b, 0, 0, 0, 0];
+    if !is_embedded || ip.is_unspecified() {
+        return None;
+    }
+    let [.., hi, lo] = s;
+    Some(Ipv4Addr::from(((hi as u32) << 16) | lo as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_ip() {
+        let public = [
+            "8.8.8.8",
+            "151.101.1.69",
+            "2606:4700::6810:84e5",
+            "::ffff:8.8.8.8",
+        ];
+        for ip in public {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        let non_public = [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "224.0.0.1",
+            "::",
+            "::1",
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            "::10.0.0.1",
+            "64:ff9b::a00:1",
+        ];
+        for ip in non_public {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn url_of_non_public_host_is_refused() {
+        let check = |url: &str| check_url(&reqwest::Url::parse(url).unwrap());
+        assert!(check("https://cdn2.thecatapi.com/images/a1.jpg").is_ok());
+        assert!(check("https://8.8.8.8/a1.jpg").is_ok());
+        assert!(check("ftp://example.com/a1.jpg").is_err());
+        assert!(check("file:///etc/passwd").is_err());
+        assert!(check("http://127.0.0.1/a1.jpg").is_err());
+        assert!(check("http://[::1]:8080/a1.jpg").is_err());
+        assert!(check("http://169.254.169.254/latest/meta-data/").is_err());
+        // 0x7f000001 is 127.0.0.1
+        assert!(check("http://0x7f000001/a1.jpg").is_err());
+    }
+
+    #[tokio::test]
+    async fn host_of_non_public_address_is_refused() {
+        // `localhost` is resolved without the network, and never connected
+        let r = fetch_image("http://localhost:9/a1.jpg").await;
+        let e = format!("{:#}", r.unwrap_err());
+        assert!(e.contains("not a public host"), "{e}");
+    }
+}
@@ -0,0 +1,277 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+use dioxus_fullstack::MultipartFormData;
+
+#[cfg(feature = "server")]
+use super::{
+    cache_image_later, get_owner_from_session, is_cat_url, store, CatStore, NewCat, SaveStatus,
+};
+
+/// the result of `import_cats()`
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ImportReport {
+    pub added: usize,
+    pub skipped: usize,
+    pub rejected: usize,
+}
+
+/// Import the cats from the uploaded file of the form field `file`
+///
+/// The file is one of:
+///   - the export of `jsonl` or `csv`, or a json array of the export records
+///   - the `cattongue.txt` journal of `backend_text`, that is an url per line
+///
+/// The urls that are already saved are skipped, and the rows that are not read
+/// or failed to save are rejected.
+#[post("/api/v1/import", session: tower_sessions::Session)]
+pub async fn import_cats(form: MultipartFormData) -> Result<ImportReport> {
+    let owner = get_owner_from_session(&session).await?;
+    let mut form = form;
+    let mut report = ImportReport::default();
+    while let Some(field) = form.next_field().await? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let file_name = field.file_name().unwrap_or("").to_string();
+        let text = field.text().await?;
+        let rows = match parse_import_text(&file_name, &text) {
+            Ok(rows) => rows,
+            Err(e) => {
+                dioxus_logger::tracing::info!("import: '{file_name}': {e}");
+                return Err(anyhow::anyhow!("Failed to read the file: {e}"));
+            }
+        };
+        for url in import_rows(store(), &owner, rows, &mut report).await {
+            cache_image_later(url);
+        }
+    }
+    Ok(report)
+}
+
+/// Save the cats of the urls of `rows`, and return the urls of the saved ones
+///
+/// The row is `None` if it is not read. The rows that are not read, not of a cat,
+/// or failed to save are rejected, and the rest are continued.
+#[cfg(feature = "server")]
+async fn import_rows(
+    store: &dyn CatStore,
+    owner: &str,
+    rows: Vec<Option<String>>,
+    report: &mut ImportReport,
+) -> Vec<String> {
+    let mut saved = Vec::new();
+    for url in rows {
+        let Some(url) = url.filter(|url| is_cat_url(url)) else {
+            report.rejected += 1;
+            continue;
+        };
+        match store.save_cat(owner, &NewCat::from_url(&url)).await {
+            Ok(SaveStatus::Saved) => {
+                report.added += 1;
+                saved.push(url);
+            }
+            Ok(SaveStatus::AlreadySaved) => report.skipped += 1,
+            Err(e) => {
+                dioxus_logger::tracing::info!("import: '{url}': {e}");
+                report.rejected += 1;
+            }
+        }
+    }
+    saved
+}
+
+/// the record of the import, the other fields of the export are ignored
+#[cfg(feature = "server")]
+#[derive(serde::Deserialize)]
+struct ImportCat {
+    url: String,
+}
+
+/// Return the urls of the rows in the text of the file, `None` for the row that is not read
+#[cfg(feature = "server")]
+pub(crate) fn parse_import_text(file_name: &str, text: &str) -> Result<Vec<Option<String>>> {
+    let text = text.trim_start_matches('\u{feff}');
+    let head = text.trim_start();
+    if head.starts_with('[') {
+        // a json array
+        let cats: Vec<ImportCat> = serde_json::from_str(text)?;
+        return Ok(cats.into_iter().map(|cat| Some(cat.url)).collect());
+    }
+    if head.starts_with('{') {
+        // json lines
+        let lines = text.lines().map(str::trim).filter(|s| !s.is_empty());
+        return Ok(lines
+            .map(|line| {
+                serde_json::from_str::<ImportCat>(line)
+                    .ok()
+                    .map(|cat| cat.url)
+            })
+            .collect());
+    }
+    if file_name.ends_with(".csv") || head.starts_with("id,") {
+        let mut records = csv_records(text).into_iter();
+        let header = records.next().unwrap_or_default();
+        let Some(col) = header.iter().position(|s| s == "url") else {
+            return Err(anyhow::anyhow!("not found the `url` column"));
+        };
+        return Ok(records.map(|record| record.into_iter().nth(col)).collect());
+    }
+    // the `cattongue.txt` journal
+    let lines = text.lines().map(str::trim).filter(|s| !s.is_empty());
+    Ok(lines.map(|s| Some(s.to_string())).collect())
+}
+
+/// Split the text of csv into the records of the fields
+///
+/// The quoted field may have the commas, the escaped quotes `""` and the newlines.
+/// The empty lines are skipped.
+#[cfg(feature = "server")]
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quote = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quote && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quote = !in_quote,
+            ',' if !in_quote => fields.push(std::mem::take(&mut field)),
+            '\r' if !in_quote => {}
+            '\n' if !in_quote => {
+                fields.push(std::mem::take(&mut field));
+                let record = std::mem::take(&mut fields);
+                if record.iter().any(|s| !s.is_empty()) {
+                    records.push(record);
+                }
+            }
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    if fields.iter().any(|s| !s.is_empty()) {
+        records.push(fields);
+    }
+    records
+}
+
+#[cfg(test)]
+#[cfg(feature = "server")]
+mod tests {
+    use super::*;
+    use crate::backends::store_memory::MemoryCatStore;
+
+    fn urls(rows: &[&str]) -> Vec<Option<String>> {
+        rows.iter().map(|s| Some(s.to_string())).collect()
+    }
+
+    #[test]
+    fn json_lines_are_parsed_row_by_row() {
+        let text = concat!(
+            "{\"id\":1,\"url\":\"https://example.com/a.png\",\"create_at\":\"\"}\n",
+            "\n",
+            "{\"id\":2,\"broken\n",
+            "{\"url\":\"https://example.com/b.png\"}\n",
+        );
+        let rows = parse_import_text("cattongue.jsonl", text).unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                Some("https://example.com/a.png".to_string()),
+                None,
+                Some("https://example.com/b.png".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn json_array_is_parsed() {
+        let text =
+            r#"[{"id":1,"url":"https://example.com/a.png"},{"url":"/api/v1/local_cats/c.png"}]"#;
+        let rows = parse_import_text("cats.json", text).unwrap();
+        assert_eq!(
+            rows,
+            urls(&["https://example.com/a.png", "/api/v1/local_cats/c.png"])
+        );
+    }
+
+    #[test]
+    fn csv_fields_are_unquoted() {
+        let text = concat!(
+            "\u{feff}id,url,create_at\r\n",
+            "1,https://example.com/a.png,2026-01-01 00:00:00\r\n",
+            "2,\"https://example.com/b,c.png\",\"2026-01-02\"\r\n",
+            "3,\"https://example.com/\"\"d\"\".png\",\n",
+            "4,\"https://example.com/e\n.png\",\"multi\nline\"\n",
+            "\n",
+            "5\n",
+        );
+        let rows = parse_import_text("cattongue.csv", text).unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                Some("https://example.com/a.png".to_string()),
+                Some("https://example.com/b,c.png".to_string()),
+                Some("https://example.com/\"d\".png".to_string()),
+                Some("https://example.com/e\n.png".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_of_only_header_is_empty() {
+        let rows = parse_import_text("cattongue.csv", "id,url,create_at\n").unwrap();
+        assert!(rows.is_empty());
+        // The `url` column is needed
+        assert!(parse_import_text("cattongue.csv", "id,name\n1,a\n").is_err());
+    }
+
+    #[test]
+    fn journal_is_an_url_per_line() {
+        let text = "https://example.com/a.png\n\n  https://example.com/b.png  \n";
+        let rows = parse_import_text("cattongue.txt", text).unwrap();
+        assert_eq!(
+            rows,
+            urls(&["https://example.com/a.png", "https://example.com/b.png"])
+        );
+    }
+
+    #[tokio::test]
+    async fn rows_are_added_skipped_or_rejected() {
+        let store = MemoryCatStore::new();
+        let mut rows = urls(&[
+            "https://example.com/a.png",
+            "https://example.com/a.png",
+            "not a url",
+            "javascript:alert(1)",
+            "https://example.com/b.png",
+        ]);
+        rows.push(None);
+        let mut report = ImportReport::default();
+        let saved = import_rows(&store, "owner", rows, &mut report).await;
+        assert_eq!(
+            saved,
+            vec![
+                "https://example.com/a.png".to_string(),
+                "https://example.com/b.png".to_string(),
+            ]
+        );
+        let expected = ImportReport {
+            added: 2,
+            skipped: 1,
+            rejected: 3,
+        };
+        assert_eq!(report, expected);
+        // The saved ones are skipped at the next import
+        let mut report = ImportReport::default();
+        let rows = urls(&["https://example.com/b.png"]);
+        assert!(import_rows(&store, "owner", rows, &mut report)
+            .await
+            .is_empty());
+        assert_eq!(report.skipped, 1);
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tower_sessions::cookie::time::{Duration, OffsetDateTime};
+use tower_sessions::session_store::ExpiredDeletion;
+
+use super::{config, store, AnySessionStore};
+
+// The router may be rebuilt by hot-reloading, then the task is started only once
+static IS_STARTED: AtomicBool = AtomicBool::new(false);
+
+// The first maintenance waits for the startup of the server
+const FIRST_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Start the maintenance task of the databases, that runs periodically.
+///
+///   - `maintenance.interval_hours`: the interval (default `24`), `0` disables it
+///   - `maintenance.retention_days`: the days, that the cats of the abandoned owners
+///     are kept, the owners without a device session nor a user account.
+///     They are kept forever, if it is `0` (default).
+///   - `maintenance.trash_days`: the days, that the deleted cats are kept in the trash
+///     (default `30`), they are kept forever, if it is `0`.
+///
+/// The maintenance deletes the expired sessions, the device sessions and the cats
+/// over the retention, the cats in the trash over the days, and the orphaned rows,
+/// then optimizes the databases.
+pub fn start_maintenance(session_store: AnySessionStore) -> Result<()> {
+    let hours = config().maintenance.interval_hours;
+    let retention_days = config().maintenance.retention_days;
+    let trash_days = config().maintenance.trash_days;
+    if hours == 0 {
+        dioxus_logger::tracing::info!("maintenance: disabled");
+        return Ok(());
+    }
+    if IS_STARTED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let retention_days = (retention_days > 0).then_some(retention_days);
+    let trash_days = (trash_days > 0).then_some(trash_days);
+    let interval = std::time::Duration::from_secs(hours * 60 * 60);
+    tokio::spawn(async move {
+        tokio::time::sleep(FIRST_DELAY).await;
+        loop {
+            if let Err(e) = run_maintenance(&session_store, retention_days, trash_days).await {
+                dioxus_logger::tracing::warn!("maintenance: {e}");
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+    Ok(())
+}
+
+/// Run the maintenance once
+async fn run_maintenance(
+    session_store: &AnySessionStore,
+    retention_days: Option<i64>,
+    trash_days: Option<i64>,
+) -> Result<()> {
+    session_store
+        .delete_expired()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to delete the expired sessions: {e}"))?;
+    dioxus_logger::tracing::info!("maintenance: deleted the expired sessions");
+    if let Some(days) = retention_days {
+        let before = db_timestamp(OffsetDateTime::now_utc() - Duration::days(days));
+        let count = store().delete_stale_device_sessions(&before).await?;
+        dioxus_logger::tracing::info!(
+            "maintenance: deleted {count} device sessions before {before}"
+        );
+        let count = store().delete_abandoned_cats(&before).await?;
+        dioxus_logger::tracing::info!("maintenance: deleted {count} cats of the abandoned owners");
+    }
+    if let Some(days) = trash_days {
+        let before = db_timestamp(OffsetDateTime::now_utc() - Duration::days(days));
+        let count = store().purge_trash(&before).await?;
+        dioxus_logger::tracing::info!(
+            "maintenance: purged {count} cats in the trash before {before}"
+        );
+    }
+    let (owners, origins) = store().delete_orphans().await?;
+    dioxus_logger::tracing::info!("maintenance: deleted {owners} owners and {origins} origins");
+    store().optimize().await?;
+    session_store.optimize().await?;
+    dioxus_logger::tracing::info!("maintenance: optimized the databases");
+    Ok(())
+}
+
+/// Reclaim the free space of the sqlite database, if it is over a quarter,
+/// and update the statistics
+///
+/// Return true, if it is vacuumed.
+pub(crate) async fn optimize_sqlite(pool: &sqlx::sqlite::SqlitePool) -> Result<bool> {
+    let page_count: i64 = sqlx::query_scalar(r#"PRAGMA page_count"#)
+        .fetch_one(pool)
+        .await?;
+    let freelist_count: i64 = sqlx::query_scalar(r#"PRAGMA freelist_count"#)
+        .fetch_one(pool)
+        .await?;
+    let is_vacuum = freelist_count > 0 && freelist_count * 4 > page_count;
+    if is_vacuum {
+        sqlx::query(r#"VACUUM"#).execute(pool).await?;
+        dioxus_logger::tracing::info!(
+            "maintenance: vacuumed {freelist_count} of {page_count} pages"
+        );
+    }
+    sqlx::query(r#"PRAGMA optimize"#).execute(pool).await?;
+    Ok(is_vacuum)
+}
+
+/// Return the time as the same format as sqlite `CURRENT_TIMESTAMP`
+pub(crate) fn db_timestamp(t: OffsetDateTime) -> String {
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        t.year(),
+        t.month() as u8,
+        t.day(),
+        t.hour(),
+        t.minute(),
+        t.second()
+    )
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+
+use super::Identity;
+
+#[cfg(feature = "server")]
+use super::{get_owner_from_session, set_session_owner, store, unix_now};
+
+/// the pairing code, that the other device enters to be linked
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PairingCode {
+    /// e.g. `ABCD-2345`
+    pub code: String,
+    /// the seconds until the code expires
+    pub expires_in: i64,
+}
+
+#[cfg(feature = "server")]
+const PAIRING_CODE_TTL: i64 = 5 * 60;
+
+// The characters that are not confused each other, e.g. `0` and `O`
+#[cfg(feature = "server")]
+const CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+#[cfg(feature = "server")]
+const CODE_LEN: usize = 8;
+
+/// Return a new pairing code of the owner of this session
+#[post("/api/v1/pairing", session: tower_sessions::Session)]
+pub async fn create_pairing_code() -> Result<PairingCode> {
+    let owner = get_owner_from_session(&session).await?;
+    let code = new_code();
+    let now = unix_now();
+    store()
+        .create_pairing_code(&code, &owner, now, now + PAIRING_CODE_TTL)
+        .await?;
+    Ok(PairingCode {
+        code: format!("{}-{}", &code[..4], &code[4..]),
+        expires_in: PAIRING_CODE_TTL,
+    })
+}
+
+/// Link this device to the device that shows `code`
+///
+/// The cats of this device are moved to the owner of the code,
+/// then the both devices have the same favorites.
+/// Return the identity of the linked owner, as `SessionCheck::identity`.
+#[post("/api/v1/pairing/link", session: tower_sessions::Session)]
+pub async fn link_device(code: String) -> Result<Identity> {
+    let owner = get_owner_from_session(&session).await?;
+    // The cats of the account are never moved
+    if store().user_of_owner(&owner).await?.is_some() {
+        return Err(anyhow::anyhow!(
+            "this device is logged in, log in the account on the other device instead"
+        ));
+    }
+    let code: String = code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let Some(linked_owner) = store().take_pairing_code(&code, unix_now()).await? else {
+        return Err(anyhow::anyhow!("the pairing code is invalid or expired"));
+    };
+    if linked_owner != owner {
+        let count = store().merge_owner(&owner, &linked_owner).await?;
+        dioxus_logger::tracing::info!("linked the device, moved {count} cats");
+    }
+    set_session_owner(&session, &linked_owner).await
+}
+
+#[cfg(feature = "server")]
+fn new_code() -> String {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    (0..CODE_LEN)
+        .map(|_| CODE_CHARS[rng.gen_range(0..CODE_CHARS.len())] as char)
+        .collect()
+}
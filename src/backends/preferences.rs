@@ -0,0 +1,91 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+
+#[cfg(feature = "server")]
+use super::{get_owner_from_session, store};
+
+/// the layout of the favorites
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FavoritesLayout {
+    /// the dense grid of the thumbnails
+    #[default]
+    Grid,
+    /// the single column of the large images
+    List,
+    /// the columns of the images in the real aspect ratio
+    Masonry,
+}
+
+impl FavoritesLayout {
+    pub const ALL: [Self; 3] = [Self::Grid, Self::List, Self::Masonry];
+
+    /// Return the name, that is kept in the database and used as the css class
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Grid => "grid",
+            Self::List => "list",
+            Self::Masonry => "masonry",
+        }
+    }
+
+    /// Return the layout of the name, or `None` if it is unknown
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|layout| layout.as_str() == name)
+    }
+}
+
+/// the preferences of the owner of cats, that follow the user account over the devices
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub favorites_layout: FavoritesLayout,
+    /// true, if the favorites are loaded by the scroll instead of the pages
+    pub infinite_scroll: bool,
+}
+
+/// Return the preferences of the owner of this session
+#[get("/api/v1/preferences", session: tower_sessions::Session)]
+pub async fn get_preferences() -> Result<Preferences> {
+    let owner = get_owner_from_session(&session).await?;
+    let mut r = Preferences::default();
+    // The unknown names and values are ignored, e.g. by the older binary
+    for (name, value) in store().list_preferences(&owner).await? {
+        match name.as_str() {
+            "favorites_layout" => {
+                if let Some(layout) = FavoritesLayout::from_name(&value) {
+                    r.favorites_layout = layout;
+                }
+            }
+            "infinite_scroll" => r.infinite_scroll = value == "true",
+            _ => {}
+        }
+    }
+    Ok(r)
+}
+
+/// Save the preferences of the owner of this session
+#[post("/api/v1/preferences", session: tower_sessions::Session)]
+pub async fn set_preferences(preferences: Preferences) -> Result<()> {
+    let owner = get_owner_from_session(&session).await?;
+    let store = store();
+    store
+        .set_preference(
+            &owner,
+            "favorites_layout",
+            preferences.favorites_layout.as_str(),
+        )
+        .await?;
+    store
+        .set_preference(
+            &owner,
+            "infinite_scroll",
+            if preferences.infinite_scroll {
+                "true"
+            } else {
+                "false"
+            },
+        )
+        .await?;
+    Ok(())
+}
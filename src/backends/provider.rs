@@ -0,0 +1,116 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+use dioxus_fullstack::FileStream;
+
+use super::NewCat;
+
+#[cfg(feature = "server")]
+use dioxus::fullstack::Lazy;
+
+#[cfg(feature = "server")]
+use super::provider_local::LocalDirProvider;
+
+#[cfg(feature = "server")]
+use super::ProviderKind;
+
+#[cfg(feature = "server")]
+use super::provider_randomcat::RandomCatProvider;
+
+#[cfg(feature = "server")]
+use super::provider_thecatapi::TheCatApiProvider;
+
+/// the source of the random cat images
+#[cfg(feature = "server")]
+#[async_trait::async_trait]
+pub trait CatProvider: Send + Sync {
+    /// Return the name of the provider, that is saved with the cat
+    fn name(&self) -> &'static str;
+
+    /// Return a random cat
+    async fn next_cat(&self) -> Result<NewCat>;
+}
+
+// The provider is only available to server code
+#[cfg(feature = "server")]
+static PROVIDER: Lazy<Box<dyn CatProvider>> = Lazy::lazy();
+
+/// Create the cat provider from the configuration.
+///
+/// The provider is selected by `provider.kind`:
+///   - `thecatapi`: <https://thecatapi.com> (default),
+///     with the optional api key of `provider.thecatapi_key`
+///   - `randomcat`: <https://aws.random.cat>
+///   - `local`: the images in the directory of `provider.local_cats_dir`,
+///     that are served by this server
+///
+/// `provider.url` overrides the api url of `thecatapi` and `randomcat`,
+/// e.g. a local mock server.
+#[cfg(feature = "server")]
+pub async fn init_provider() -> Result<()> {
+    let config = &super::config().provider;
+    let api_url = config.url.clone();
+    let provider: Box<dyn CatProvider> = match config.kind {
+        ProviderKind::TheCatApi => {
+            let api_key = config.thecatapi_key.clone();
+            Box::new(TheCatApiProvider::new(api_url, api_key))
+        }
+        ProviderKind::RandomCat => Box::new(RandomCatProvider::new(api_url)),
+        ProviderKind::Local => Box::new(LocalDirProvider::new(local_cats_dir())?),
+    };
+    dioxus_logger::tracing::info!("cat provider: {}", provider.name());
+    // The router may be rebuilt by hot-reloading, then keep the first provider
+    let _ = PROVIDER.try_set(provider);
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+fn local_cats_dir() -> std::path::PathBuf {
+    match &super::config().provider.local_cats_dir {
+        Some(dir) => dir.clone(),
+        None => super::data_base_dir().join("cats"),
+    }
+}
+
+/// Return a random cat from the provider
+#[get("/api/v1/next_cat")]
+pub async fn next_cat() -> Result<NewCat> {
+    PROVIDER.get().next_cat().await
+}
+
+/// Return the image of the `local` provider
+#[get("/api/v1/local_cats/{name}")]
+pub async fn local_cat_image(name: String) -> Result<FileStream> {
+    let path = LocalDirProvider::image_path(&local_cats_dir(), &name)?;
+    let file = tokio::fs::File::open(&path).await?;
+    let size = file.metadata().await?.len();
+    let body = dioxus_fullstack::body::Body::from_stream(tokio_util::io::ReaderStream::new(file));
+    Ok(FileStream::from_raw(
+        name.clone(),
+        Some(size),
+        LocalDirProvider::content_type(&name).to_string(),
+        body.into_data_stream(),
+    ))
+}
+
+/// Read the image of `url` of the `local` provider
+#[cfg(feature = "server")]
+pub(crate) async fn read_local_cat_image(url: &str) -> Result<Vec<u8>> {
+    let Some(name) = url.strip_prefix("/api/v1/local_cats/") else {
+        return Err(anyhow::anyhow!(
+            "not an image of the local provider: '{url}'"
+        ));
+    };
+    let path = LocalDirProvider::image_path(&local_cats_dir(), name)?;
+    Ok(tokio::fs::read(&path).await?)
+}
+
+/// Return the url that the `img` element can load
+///
+/// The `local` provider returns the url that is relative to this server.
+pub fn image_src(url: &str) -> String {
+    if url.starts_with('/') {
+        format!("{}{url}", dioxus_fullstack::get_server_url())
+    } else {
+        url.to_string()
+    }
+}
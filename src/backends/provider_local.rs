@@ -0,0 +1,165 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use super::{CatProvider, NewCat};
+
+// Only the raster images, the svg can have the scripts
+const IMAGE_EXTS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp"];
+
+/// the provider of the images in a local directory
+///
+/// The images are served by `local_cat_image()`, so this works with no network.
+pub(crate) struct LocalDirProvider {
+    dir: PathBuf,
+}
+
+impl LocalDirProvider {
+    pub(crate) fn new(dir: PathBuf) -> Result<Self> {
+        if !dir.is_dir() {
+            return Err(anyhow::anyhow!(
+                "not found the directory of the local cats: '{}'",
+                dir.display()
+            ));
+        }
+        Ok(Self { dir })
+    }
+
+    /// Return the path of the image `name` in `dir`
+    ///
+    /// `name` must be a plain file name of an image, not a path.
+    pub(crate) fn image_path(dir: &Path, name: &str) -> Result<PathBuf> {
+        let is_plain = !name.is_empty()
+            && !name.starts_with('.')
+            && !name.contains(['/', '\\'])
+            && is_image_name(name);
+        if !is_plain {
+            return Err(anyhow::anyhow!("invalid image name: '{name}'"));
+        }
+        Ok(dir.join(name))
+    }
+
+    /// Return the content type of the image `name`
+    pub(crate) fn content_type(name: &str) -> &'static str {
+        let ext = name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("jpg" | "jpeg") => "image/jpeg",
+            Some("png") => "image/png",
+            Some("gif") => "image/gif",
+            Some("webp") => "image/webp",
+            _ => "application/octet-stream",
+        }
+    }
+
+    fn image_names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            if let Some(name) = entry.file_name().to_str() {
+                if !name.starts_with('.') && is_image_name(name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+}
+
+fn is_image_name(name: &str) -> bool {
+    match name.rsplit_once('.') {
+        Some((_, ext)) => IMAGE_EXTS.contains(&ext.to_ascii_lowercase().as_str()),
+        None => false,
+    }
+}
+
+#[async_trait::async_trait]
+impl CatProvider for LocalDirProvider {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn next_cat(&self) -> Result<NewCat> {
+        let names = self.image_names()?;
+        if names.is_empty() {
+            return Err(anyhow::anyhow!(
+                "no image in the directory of the local cats: '{}'",
+                self.dir.display()
+            ));
+        }
+        let name = &names[rand::random::<usize>() % names.len()];
+        Ok(NewCat {
+            url: format!("/api/v1/local_cats/{name}"),
+            provider: self.name().to_string(),
+            provider_id: Some(name.clone()),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The directory of the local cats, that is made again on each run
+    fn cats_dir(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cattongue_test_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub.png")).unwrap();
+        for file in files {
+            std::fs::write(dir.join(file), b"").unwrap();
+        }
+        dir
+    }
+
+    #[tokio::test]
+    async fn next_cat_without_network() {
+        let dir = cats_dir(
+            "local_next_cat",
+            &["a.png", "b.JPG", ".c.png", "d.svg", "e.txt"],
+        );
+        let provider = LocalDirProvider::new(dir.clone()).unwrap();
+        assert_eq!(provider.image_names().unwrap(), ["a.png", "b.JPG"]);
+        for _ in 0..10 {
+            let cat = provider.next_cat().await.unwrap();
+            let name = cat.provider_id.unwrap();
+            assert!(name == "a.png" || name == "b.JPG", "{name}");
+            assert_eq!(cat.url, format!("/api/v1/local_cats/{name}"));
+            assert_eq!(cat.provider, "local");
+        }
+        //
+        let empty = LocalDirProvider::new(cats_dir("local_empty", &["d.svg"])).unwrap();
+        assert!(empty.next_cat().await.is_err());
+        assert!(LocalDirProvider::new(dir.join("a.png")).is_err());
+    }
+
+    #[test]
+    fn image_path_is_a_raster_image_in_dir() {
+        let dir = Path::new("/cats");
+        assert_eq!(
+            LocalDirProvider::image_path(dir, "a.png").unwrap(),
+            dir.join("a.png")
+        );
+        for name in [
+            "",
+            ".a.png",
+            "../a.png",
+            "sub/a.png",
+            "sub\\a.png",
+            "a.svg",
+            "a",
+        ] {
+            assert!(LocalDirProvider::image_path(dir, name).is_err(), "{name}");
+        }
+        assert_eq!(LocalDirProvider::content_type("a.JPEG"), "image/jpeg");
+        assert_eq!(LocalDirProvider::content_type("a.webp"), "image/webp");
+        assert_eq!(
+            LocalDirProvider::content_type("a.svg"),
+            "application/octet-stream"
+        );
+    }
+}
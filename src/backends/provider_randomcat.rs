@@ -0,0 +1,44 @@
+use anyhow::Result;
+
+use super::{CatProvider, NewCat};
+
+const API_URL: &str = "https://aws.random.cat/meow";
+
+/// the provider of <https://aws.random.cat>
+pub(crate) struct RandomCatProvider {
+    client: reqwest::Client,
+    api_url: String,
+}
+
+impl RandomCatProvider {
+    pub(crate) fn new(api_url: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: api_url.unwrap_or_else(|| API_URL.to_string()),
+        }
+    }
+}
+
+// e.g. {"file":"https:\/\/purr.objects-us-east-1.dream.io\/i\/1222.jpg"}
+#[derive(serde::Deserialize)]
+struct Meow {
+    file: String,
+}
+
+#[async_trait::async_trait]
+impl CatProvider for RandomCatProvider {
+    fn name(&self) -> &'static str {
+        "randomcat"
+    }
+
+    async fn next_cat(&self) -> Result<NewCat> {
+        let resp = self.client.get(&self.api_url).send().await?;
+        let meow = resp.error_for_status()?.json::<Meow>().await?;
+        // random.cat has no metadata, the url is the identity of the image
+        Ok(NewCat {
+            url: meow.file,
+            provider: self.name().to_string(),
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+
+use super::{CatProvider, NewCat};
+
+const API_URL: &str = "https://api.thecatapi.com/v1/images/search";
+
+/// the provider of <https://thecatapi.com>
+pub(crate) struct TheCatApiProvider {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: Option<String>,
+}
+
+impl TheCatApiProvider {
+    pub(crate) fn new(api_url: Option<String>, api_key: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: api_url.unwrap_or_else(|| API_URL.to_string()),
+            api_key,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct CatApi {
+    id: String,
+    url: String,
+    width: i64,
+    height: i64,
+    #[serde(default)]
+    breeds: Vec<CatApiBreed>,
+}
+
+#[derive(serde::Deserialize)]
+struct CatApiBreed {
+    name: String,
+}
+
+#[async_trait::async_trait]
+impl CatProvider for TheCatApiProvider {
+    fn name(&self) -> &'static str {
+        "thecatapi"
+    }
+
+    async fn next_cat(&self) -> Result<NewCat> {
+        let mut req = self.client.get(&self.api_url);
+        if let Some(api_key) = &self.api_key {
+            req = req.header("x-api-key", api_key);
+        }
+        let resp = req.send().await?.error_for_status()?;
+        let Some(cat) = resp.json::<Vec<CatApi>>().await?.into_iter().next() else {
+            return Err(anyhow::anyhow!("thecatapi returned no cat"));
+        };
+        Ok(NewCat {
+            url: cat.url,
+            provider: self.name().to_string(),
+            provider_id: Some(cat.id),
+            width: Some(cat.width),
+            height: Some(cat.height),
+            breeds: cat.breeds.into_iter().map(|b| b.name).collect(),
+        })
+    }
+}
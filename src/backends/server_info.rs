@@ -0,0 +1,19 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+
+/// the information of the server, for the check of the connection from the client
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ServerInfo {
+    /// the name of the package, `cattongue`
+    pub name: String,
+    pub version: String,
+}
+
+/// Return the information of this server, without a session
+#[get("/api/v1/server_info")]
+pub async fn server_info() -> Result<ServerInfo> {
+    Ok(ServerInfo {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    })
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use dioxus::server::axum::Router;
+use tower_sessions::cookie::{time::Duration, Key, SameSite};
+use tower_sessions::{Expiry, SessionManagerLayer};
+
+use super::{config, cookie_key, AnySessionStore, CookieConfig, CookieProtection, CookieSameSite};
+
+impl CookieConfig {
+    /// Return the warnings of the insecure settings
+    pub fn insecure_warnings(&self) -> Vec<&'static str> {
+        let mut warnings = Vec::new();
+        if !self.secure {
+            warnings.push("the session cookie is sent over http, set `cookie.secure = true`");
+        }
+        if self.protection == CookieProtection::Plain {
+            warnings.push("the session cookie is not signed, set `cookie.protection`");
+        }
+        warnings
+    }
+}
+
+/// Add the session layer to `router`, with the settings of `cookie` of the configuration
+///
+/// The key of the signed or private cookies is derived from the secret key
+/// of `init_identity()`, that must be called before.
+pub fn with_session_layer(router: Router, store: AnySessionStore) -> Result<Router> {
+    let config = &config().cookie;
+    dioxus_logger::tracing::info!("session cookie: {config:?}");
+    // The insecure settings are usual on the development
+    for warning in config.insecure_warnings() {
+        if cfg!(debug_assertions) {
+            dioxus_logger::tracing::debug!("insecure: {warning}");
+        } else {
+            dioxus_logger::tracing::warn!("insecure: {warning}");
+        }
+    }
+    let mut layer = SessionManagerLayer::new(store)
+        .with_name(config.name.clone())
+        .with_path(config.path.clone().unwrap_or_else(|| "/".to_string()))
+        .with_secure(config.secure)
+        .with_http_only(true)
+        .with_always_save(false)
+        .with_same_site(match config.same_site {
+            CookieSameSite::Strict => SameSite::Strict,
+            CookieSameSite::Lax => SameSite::Lax,
+            CookieSameSite::None => SameSite::None,
+        })
+        .with_expiry(match config.expiry_days {
+            0 => Expiry::OnSessionEnd,
+            days => Expiry::OnInactivity(Duration::days(days)),
+        });
+    if let Some(domain) = &config.domain {
+        layer = layer.with_domain(domain.clone());
+    }
+    let router = match config.protection {
+        CookieProtection::Plain => router.layer(layer),
+        CookieProtection::Signed => router.layer(layer.with_signed(Key::from(&cookie_key()))),
+        CookieProtection::Private => router.layer(layer.with_private(Key::from(&cookie_key()))),
+    };
+    Ok(router)
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+
+#[cfg(feature = "server")]
+use super::{get_owner_from_session, get_sid_from_session, store};
+
+/// the device session in the list of sessions, that is a browser or an app
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DeviceSession {
+    pub sid: String,
+    /// the name of the browser, e.g. `Chrome 120.0`
+    pub browser: String,
+    /// the name of the os, e.g. `Windows 10`
+    pub os: String,
+    pub create_at: String,
+    pub last_seen_at: String,
+    /// true, if it is the session of this device
+    pub is_current: bool,
+}
+
+/// Return the device sessions of the owner of this session
+#[get("/api/v1/sessions", session: tower_sessions::Session)]
+pub async fn list_sessions() -> Result<Vec<DeviceSession>> {
+    let owner = get_owner_from_session(&session).await?;
+    let sid = get_sid_from_session(&session).await?;
+    let r = store()
+        .list_device_sessions(&owner)
+        .await?
+        .into_iter()
+        .map(|s| DeviceSession {
+            is_current: s.sid == sid,
+            sid: s.sid,
+            browser: s.browser,
+            os: s.os,
+            create_at: s.create_at,
+            last_seen_at: s.last_seen_at,
+        })
+        .collect();
+    Ok(r)
+}
+
+/// Revoke the device session `sid` of the owner of this session
+///
+/// The revoked device gets a new identity at the next visit.
+/// The session of this device is not revoked here, it is the log out.
+#[delete("/api/v1/sessions/{sid}", session: tower_sessions::Session)]
+pub async fn revoke_session(sid: String) -> Result<()> {
+    let owner = get_owner_from_session(&session).await?;
+    if get_sid_from_session(&session).await? == sid {
+        return Err(anyhow::anyhow!("this is the session of this device"));
+    }
+    if !store().delete_device_session(&owner, &sid).await? {
+        return Err(anyhow::anyhow!("the session is not found"));
+    }
+    Ok(())
+}
+
+/// Revoke all the device sessions of the owner of this session,
+/// and return true if the session of this device is revoked too
+///
+/// The session of this device is kept, if it is not logged in,
+/// because the cats of it are never reached again without the session.
+#[post("/api/v1/sessions/logout_everywhere", session: tower_sessions::Session)]
+pub async fn logout_everywhere() -> Result<bool> {
+    let owner = get_owner_from_session(&session).await?;
+    let sid = get_sid_from_session(&session).await?;
+    let is_logged_in = store().user_of_owner(&owner).await?.is_some();
+    let keep_sid = (!is_logged_in).then_some(sid.as_str());
+    let count = store().delete_device_sessions(&owner, keep_sid).await?;
+    dioxus_logger::tracing::info!("revoked {count} sessions");
+    if is_logged_in {
+        session.flush().await?;
+    }
+    Ok(is_logged_in)
+}
@@ -0,0 +1,273 @@
+use anyhow::Result;
+use dioxus::fullstack::Lazy;
+
+use super::{config, CatCursor, CatItem, NewCat, SaveStatus, StoreKind, TrashItem};
+
+use super::store_memory::MemoryCatStore;
+use super::store_sqlite::SqliteCatStore;
+
+#[cfg(feature = "postgres")]
+use super::store_postgres::PostgresCatStore;
+
+/// the storage of cats behind the server functions
+///
+/// The owner `bicmid` is always the identity taken from the session,
+/// the server functions never pass a client supplied owner here.
+#[async_trait::async_trait]
+pub trait CatStore: Send + Sync {
+    /// Return at most `limit` cats next to `cursor` and their url, the newest first
+    ///
+    /// The url is the one of the cached image, if it is cached.
+    /// The cats in the trash are excluded.
+    async fn list_cats_page(
+        &self,
+        bicmid: &str,
+        cursor: CatCursor,
+        limit: i64,
+    ) -> Result<Vec<CatItem>>;
+
+    /// Return the count of cats, except the ones in the trash
+    async fn count_of_cats(&self, bicmid: &str) -> Result<usize>;
+
+    /// Return the preferences of the owner, the pairs of the name and the value
+    async fn list_preferences(&self, owner: &str) -> Result<Vec<(String, String)>>;
+
+    /// Set the preference of the owner
+    async fn set_preference(&self, owner: &str, name: &str, value: &str) -> Result<()>;
+
+    /// Move the cats of `ids` to the trash, that are owned by `bicmid`
+    ///
+    /// Return the count of moved cats.
+    async fn delete_cats(&self, bicmid: &str, ids: &[i64]) -> Result<u64>;
+
+    /// Return the cats in the trash, the latest deleted first
+    async fn list_trash(&self, bicmid: &str) -> Result<Vec<TrashItem>>;
+
+    /// Restore the cats of `ids` from the trash, that are owned by `bicmid`
+    ///
+    /// Return the count of restored cats.
+    async fn restore_cats(&self, bicmid: &str, ids: &[i64]) -> Result<u64>;
+
+    /// Delete the cat in the trash permanently, if it is owned by `bicmid`
+    ///
+    /// Return true, if it is deleted.
+    async fn purge_cat(&self, bicmid: &str, id: i64) -> Result<bool>;
+
+    /// Delete the cats permanently, that are moved to the trash before `before`
+    ///
+    /// Return the count of deleted cats.
+    async fn purge_trash(&self, before: &str) -> Result<u64>;
+
+    /// Save the cat with the metadata
+    ///
+    /// The cat is unique per `bicmid` and url, and per `bicmid` and provider id,
+    /// a duplicate is not saved. The duplicate in the trash is replaced by the new one.
+    async fn save_cat(&self, bicmid: &str, cat: &NewCat) -> Result<SaveStatus>;
+
+    /// Rename the owner `from` to `to`, if `to` owns no cat yet
+    ///
+    /// Return true, if it is renamed.
+    async fn rename_owner(&self, from: &str, to: &str) -> Result<bool>;
+
+    /// Move the cats, the device sessions and the preferences of the owner `from` to the owner `to`
+    ///
+    /// The cats and the preferences that `to` already has are dropped,
+    /// except the cat in the trash of `to` is replaced by the live one of `from`.
+    /// Return the count of moved cats.
+    async fn merge_owner(&self, from: &str, to: &str) -> Result<u64>;
+
+    /// Create the user account of `owner`
+    ///
+    /// Return false, if `name` is already used.
+    async fn create_user(&self, user: &UserRecord) -> Result<bool>;
+
+    /// Return the user account of `name`
+    async fn find_user(&self, name: &str) -> Result<Option<UserRecord>>;
+
+    /// Return the name of the user account of `owner`
+    async fn user_of_owner(&self, owner: &str) -> Result<Option<String>>;
+
+    /// Save the pairing code of `owner`, that expires at `expire_at` in unix time
+    ///
+    /// The codes that are expired at `now` are deleted.
+    async fn create_pairing_code(
+        &self,
+        code: &str,
+        owner: &str,
+        now: i64,
+        expire_at: i64,
+    ) -> Result<()>;
+
+    /// Return the owner of the pairing code, if it is not expired at `now`
+    ///
+    /// The code is deleted, so it is used only once.
+    async fn take_pairing_code(&self, code: &str, now: i64) -> Result<Option<String>>;
+
+    /// Save the device session `sid` of `owner`, with the names of the browser and the os
+    async fn create_device_session(
+        &self,
+        sid: &str,
+        owner: &str,
+        browser: &str,
+        os: &str,
+    ) -> Result<()>;
+
+    /// Update the last seen time of the device session, and return the owner of it
+    ///
+    /// Return `None`, if the device session is revoked.
+    async fn touch_device_session(&self, sid: &str) -> Result<Option<String>>;
+
+    /// Change the owner of the device session, e.g. by the log in
+    async fn set_device_session_owner(&self, sid: &str, owner: &str) -> Result<()>;
+
+    /// Return the device sessions of `owner`, in descending order of the last seen time
+    async fn list_device_sessions(&self, owner: &str) -> Result<Vec<DeviceSessionRecord>>;
+
+    /// Delete the device session, if it is owned by `owner`
+    ///
+    /// Return true, if it is deleted.
+    async fn delete_device_session(&self, owner: &str, sid: &str) -> Result<bool>;
+
+    /// Delete the device sessions of `owner`, except `keep_sid`
+    ///
+    /// Return the count of deleted sessions.
+    async fn delete_device_sessions(&self, owner: &str, keep_sid: Option<&str>) -> Result<u64>;
+
+    /// Delete the device sessions, that are last seen before `before`
+    ///
+    /// Return the count of deleted sessions.
+    async fn delete_stale_device_sessions(&self, before: &str) -> Result<u64>;
+
+    /// Delete the cats of the abandoned owners, that have neither a device session
+    /// nor a user account, and saved no cat since `before`
+    ///
+    /// Return the count of deleted cats.
+    async fn delete_abandoned_cats(&self, before: &str) -> Result<u64>;
+
+    /// Delete the `Bicmid` and the `UrlOrigin`, that no cat refers to,
+    /// and the preferences of the owners, that have neither a device session nor a user account
+    ///
+    /// Return the counts of deleted owners and origins.
+    async fn delete_orphans(&self) -> Result<(u64, u64)>;
+
+    /// Reclaim the free space and update the statistics of the database, as needed
+    async fn optimize(&self) -> Result<()>;
+
+    /// Set the hash of the cached image to the cats of `url`
+    async fn set_image_hash(&self, url: &str, hash: &str) -> Result<()>;
+
+    /// Return at most `limit` cats after `after_id`, in ascending order of id,
+    /// except the ones in the trash
+    async fn export_cats(&self, bicmid: &str, after_id: i64, limit: i64) -> Result<Vec<CatRecord>>;
+
+    /// Return the cats of `ids`, in ascending order of id, except the ones in the trash
+    async fn select_cats(&self, bicmid: &str, ids: &[i64]) -> Result<Vec<CatRecord>>;
+
+    /// Add `tag` to the cats of `ids`, or remove it if `is_tagged` is false,
+    /// except the ones in the trash
+    ///
+    /// Return the count of changed cats.
+    async fn tag_cats(&self, bicmid: &str, ids: &[i64], tag: &str, is_tagged: bool) -> Result<u64>;
+
+    /// Move the cats of `ids` to `album`, or out of the album if it is `None`,
+    /// except the ones in the trash
+    ///
+    /// Return the count of moved cats.
+    async fn move_cats(&self, bicmid: &str, ids: &[i64], album: Option<&str>) -> Result<u64>;
+
+    /// Return the names of the albums, that have the cats of `bicmid`, in ascending order
+    async fn list_albums(&self, bicmid: &str) -> Result<Vec<String>>;
+}
+
+/// Add `tag` to `tags` in ascending order, or remove it if `is_tagged` is false
+///
+/// Return true, if `tags` is changed.
+pub(crate) fn edit_tags(tags: &mut Vec<String>, tag: &str, is_tagged: bool) -> bool {
+    match (tags.binary_search_by(|t| t.as_str().cmp(tag)), is_tagged) {
+        (Err(at), true) => tags.insert(at, tag.to_string()),
+        (Ok(at), false) => {
+            tags.remove(at);
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// the full record of a saved cat, for the export and the import
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CatRecord {
+    pub id: i64,
+    pub url: String,
+    pub create_at: String,
+    /// the hash of the cached image, that is not a part of the export file
+    #[serde(skip)]
+    pub image_hash: Option<String>,
+}
+
+/// the user account, that is the owner of cats with a password
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserRecord {
+    pub name: String,
+    /// the argon2 hash in the PHC string format
+    pub password_hash: String,
+    /// the identity that owns the cats
+    pub owner: String,
+}
+
+/// the device session, that is a browser or an app with the identity token
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceSessionRecord {
+    pub sid: String,
+    /// the name of the browser, e.g. `Chrome 120.0`
+    pub browser: String,
+    /// the name of the os, e.g. `Windows 10`
+    pub os: String,
+    pub create_at: String,
+    pub last_seen_at: String,
+}
+
+// The store is only available to server code
+static STORE: Lazy<Box<dyn CatStore>> = Lazy::lazy();
+
+/// Open the cat store and apply the pending schema migrations.
+///
+/// If `database.url` is set, the store is the database of it.
+/// Otherwise the kind of store is selected by `database.store`:
+///   - `sqlite`: the persisted sqlite database (default)
+///   - `memory`: an in-memory store for the demo mode, nothing is persisted
+///
+/// This is called at server startup, so that the server refuses to start
+/// when the database is newer than this binary.
+pub async fn init_database() -> Result<()> {
+    let store: Box<dyn CatStore> = if let Some(db_url) = super::database_url() {
+        open_database_url(&db_url).await?
+    } else {
+        match config().database.store {
+            StoreKind::Sqlite => Box::new(SqliteCatStore::open().await?),
+            StoreKind::Memory => {
+                dioxus_logger::tracing::warn!("demo mode: cats are NOT persisted");
+                Box::new(MemoryCatStore::new())
+            }
+        }
+    };
+    // The router may be rebuilt by hot-reloading, then keep the first store
+    let _ = STORE.try_set(store);
+    Ok(())
+}
+
+async fn open_database_url(db_url: &str) -> Result<Box<dyn CatStore>> {
+    if super::is_postgres_url(db_url) {
+        #[cfg(feature = "postgres")]
+        return Ok(Box::new(PostgresCatStore::open(db_url).await?));
+        #[cfg(not(feature = "postgres"))]
+        return Err(anyhow::anyhow!(
+            "this binary is built without the `postgres` feature"
+        ));
+    }
+    Err(anyhow::anyhow!("unsupported database url: '{db_url}'"))
+}
+
+/// Return the cat store opened by `init_database()`
+pub(crate) fn store() -> &'static dyn CatStore {
+    STORE.get().as_ref()
+}
@@ -0,0 +1,555 @@
+use anyhow::Result;
+use std::sync::Mutex;
+
+use super::{
+    db_timestamp, edit_tags, image_url, CatCursor, CatItem, CatRecord, CatStore,
+    DeviceSessionRecord, NewCat, SaveStatus, TrashItem, UserRecord,
+};
+
+/// the cat store on memory, for tests and the demo mode
+#[derive(Default)]
+pub(crate) struct MemoryCatStore {
+    inner: Mutex<MemoryData>,
+}
+
+#[derive(Default)]
+struct MemoryData {
+    last_id: i64,
+    cats: Vec<MemoryCat>,
+    users: Vec<UserRecord>,
+    /// (code, owner, expire_at)
+    pairing_codes: Vec<(String, String, i64)>,
+    /// (owner, device session), in ascending order of the creation
+    device_sessions: Vec<(String, DeviceSessionRecord)>,
+    /// (owner, name, value)
+    preferences: Vec<(String, String, String)>,
+}
+
+struct MemoryCat {
+    id: i64,
+    bicmid: String,
+    create_at: String,
+    cat: NewCat,
+    image_hash: Option<String>,
+    deleted_at: Option<String>,
+    tags: Vec<String>,
+    album: Option<String>,
+}
+
+impl MemoryCat {
+    fn to_item(&self) -> CatItem {
+        CatItem {
+            id: self.id,
+            url: match &self.image_hash {
+                Some(hash) => image_url(hash),
+                None => self.cat.url.clone(),
+            },
+            width: self.cat.width,
+            height: self.cat.height,
+            image_hash: self.image_hash.clone(),
+            source_url: self.cat.url.clone(),
+            create_at: self.create_at.clone(),
+            tags: self.tags.clone(),
+            album: self.album.clone(),
+        }
+    }
+}
+
+impl MemoryCatStore {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, MemoryData>> {
+        self.inner
+            .lock()
+            .map_err(|_e| anyhow::anyhow!("the memory store is poisoned"))
+    }
+}
+
+#[async_trait::async_trait]
+impl CatStore for MemoryCatStore {
+    async fn list_cats_page(
+        &self,
+        bicmid: &str,
+        cursor: CatCursor,
+        limit: i64,
+    ) -> Result<Vec<CatItem>> {
+        let limit: usize = limit.try_into()?;
+        let data = self.lock()?;
+        let owned = data
+            .cats
+            .iter()
+            .filter(|cat| cat.bicmid == bicmid && cat.deleted_at.is_none());
+        let mut cats: Vec<&MemoryCat> = match cursor {
+            CatCursor::First => owned.rev().take(limit).collect(),
+            CatCursor::Older(id) => owned.rev().filter(|cat| cat.id < id).take(limit).collect(),
+            CatCursor::Newer(id) => owned.filter(|cat| cat.id > id).take(limit).collect(),
+        };
+        if matches!(cursor, CatCursor::Newer(_)) {
+            cats.reverse();
+        }
+        Ok(cats.into_iter().map(MemoryCat::to_item).collect())
+    }
+
+    async fn count_of_cats(&self, bicmid: &str) -> Result<usize> {
+        let data = self.lock()?;
+        Ok(data
+            .cats
+            .iter()
+            .filter(|cat| cat.bicmid == bicmid && cat.deleted_at.is_none())
+            .count())
+    }
+
+    async fn list_preferences(&self, owner: &str) -> Result<Vec<(String, String)>> {
+        let data = self.lock()?;
+        let mut r: Vec<_> = data
+            .preferences
+            .iter()
+            .filter(|(o, _, _)| o == owner)
+            .map(|(_, name, value)| (name.clone(), value.clone()))
+            .collect();
+        r.sort();
+        Ok(r)
+    }
+
+    async fn set_preference(&self, owner: &str, name: &str, value: &str) -> Result<()> {
+        let mut data = self.lock()?;
+        match data
+            .preferences
+            .iter_mut()
+            .find(|(o, n, _)| o == owner && n == name)
+        {
+            Some((_, _, v)) => *v = value.to_string(),
+            None => data
+                .preferences
+                .push((owner.to_string(), name.to_string(), value.to_string())),
+        }
+        Ok(())
+    }
+
+    async fn delete_cats(&self, bicmid: &str, ids: &[i64]) -> Result<u64> {
+        let mut data = self.lock()?;
+        let now = now_timestamp();
+        let mut count = 0;
+        for cat in data
+            .cats
+            .iter_mut()
+            .filter(|cat| cat.bicmid == bicmid && ids.contains(&cat.id) && cat.deleted_at.is_none())
+        {
+            cat.deleted_at = Some(now.clone());
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn list_trash(&self, bicmid: &str) -> Result<Vec<TrashItem>> {
+        let data = self.lock()?;
+        let mut r: Vec<TrashItem> = data
+            .cats
+            .iter()
+            .filter(|cat| cat.bicmid == bicmid)
+            .filter_map(|cat| {
+                Some(TrashItem {
+                    cat: cat.to_item(),
+                    deleted_at: cat.deleted_at.clone()?,
+                })
+            })
+            .collect();
+        r.sort_by(|a, b| (&b.deleted_at, b.cat.id).cmp(&(&a.deleted_at, a.cat.id)));
+        Ok(r)
+    }
+
+    async fn restore_cats(&self, bicmid: &str, ids: &[i64]) -> Result<u64> {
+        let mut data = self.lock()?;
+        let mut count = 0;
+        for cat in data
+            .cats
+            .iter_mut()
+            .filter(|cat| cat.bicmid == bicmid && ids.contains(&cat.id) && cat.deleted_at.is_some())
+        {
+            cat.deleted_at = None;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn purge_cat(&self, bicmid: &str, id: i64) -> Result<bool> {
+        let mut data = self.lock()?;
+        let count = data.cats.len();
+        data.cats
+            .retain(|cat| !(cat.bicmid == bicmid && cat.id == id && cat.deleted_at.is_some()));
+        Ok(data.cats.len() < count)
+    }
+
+    async fn purge_trash(&self, before: &str) -> Result<u64> {
+        let mut data = self.lock()?;
+        let count = data.cats.len();
+        data.cats
+            .retain(|cat| cat.deleted_at.as_deref().is_none_or(|at| at >= before));
+        Ok((count - data.cats.len()) as u64)
+    }
+
+    async fn save_cat(&self, bicmid: &str, cat: &NewCat) -> Result<SaveStatus> {
+        let mut data = self.lock()?;
+        // The duplicate in the trash is replaced by the new one
+        data.cats.retain(|a| {
+            !(a.bicmid == bicmid && a.deleted_at.is_some() && is_same_cat(&a.cat, cat))
+        });
+        let is_saved = data
+            .cats
+            .iter()
+            .any(|a| a.bicmid == bicmid && is_same_cat(&a.cat, cat));
+        if is_saved {
+            return Ok(SaveStatus::AlreadySaved);
+        }
+        data.last_id += 1;
+        let id = data.last_id;
+        data.cats.push(MemoryCat {
+            id,
+            bicmid: bicmid.to_string(),
+            create_at: now_timestamp(),
+            cat: cat.clone(),
+            image_hash: None,
+            deleted_at: None,
+            tags: Vec::new(),
+            album: None,
+        });
+        Ok(SaveStatus::Saved)
+    }
+
+    async fn rename_owner(&self, from: &str, to: &str) -> Result<bool> {
+        let mut data = self.lock()?;
+        if data.cats.iter().any(|cat| cat.bicmid == to) {
+            return Ok(false);
+        }
+        let mut is_renamed = false;
+        for cat in data.cats.iter_mut().filter(|cat| cat.bicmid == from) {
+            cat.bicmid = to.to_string();
+            is_renamed = true;
+        }
+        Ok(is_renamed)
+    }
+
+    async fn merge_owner(&self, from: &str, to: &str) -> Result<u64> {
+        if from == to {
+            return Ok(0);
+        }
+        let mut data = self.lock()?;
+        for (owner, _) in data.device_sessions.iter_mut().filter(|(o, _)| o == from) {
+            *owner = to.to_string();
+        }
+        let (moved, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut data.preferences)
+            .into_iter()
+            .partition(|(o, _, _)| o == from);
+        data.preferences = rest;
+        for (_, name, value) in moved {
+            if !data
+                .preferences
+                .iter()
+                .any(|(o, n, _)| o == to && *n == name)
+            {
+                data.preferences.push((to.to_string(), name, value));
+            }
+        }
+        let (moved, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut data.cats)
+            .into_iter()
+            .partition(|cat| cat.bicmid == from);
+        data.cats = rest;
+        let mut count = 0;
+        for mut cat in moved {
+            // The live cat wins over the one in the trash
+            if cat.deleted_at.is_none() {
+                data.cats.retain(|a| {
+                    a.bicmid != to || a.deleted_at.is_none() || !is_same_cat(&a.cat, &cat.cat)
+                });
+            }
+            let is_saved = data
+                .cats
+                .iter()
+                .any(|a| a.bicmid == to && is_same_cat(&a.cat, &cat.cat));
+            if !is_saved {
+                cat.bicmid = to.to_string();
+                data.cats.push(cat);
+                count += 1;
+            }
+        }
+        data.cats.sort_by_key(|cat| cat.id);
+        Ok(count)
+    }
+
+    async fn create_user(&self, user: &UserRecord) -> Result<bool> {
+        let mut data = self.lock()?;
+        let is_used = data
+            .users
+            .iter()
+            .any(|a| a.name == user.name || a.owner == user.owner);
+        if is_used {
+            return Ok(false);
+        }
+        data.users.push(user.clone());
+        Ok(true)
+    }
+
+    async fn find_user(&self, name: &str) -> Result<Option<UserRecord>> {
+        let data = self.lock()?;
+        Ok(data.users.iter().find(|a| a.name == name).cloned())
+    }
+
+    async fn user_of_owner(&self, owner: &str) -> Result<Option<String>> {
+        let data = self.lock()?;
+        let user = data.users.iter().find(|a| a.owner == owner);
+        Ok(user.map(|a| a.name.clone()))
+    }
+
+    async fn create_pairing_code(
+        &self,
+        code: &str,
+        owner: &str,
+        now: i64,
+        expire_at: i64,
+    ) -> Result<()> {
+        let mut data = self.lock()?;
+        data.pairing_codes.retain(|(_, _, t)| *t >= now);
+        data.pairing_codes
+            .push((code.to_string(), owner.to_string(), expire_at));
+        Ok(())
+    }
+
+    async fn take_pairing_code(&self, code: &str, now: i64) -> Result<Option<String>> {
+        let mut data = self.lock()?;
+        let Some(pos) = data.pairing_codes.iter().position(|(c, _, _)| c == code) else {
+            return Ok(None);
+        };
+        let (_, owner, expire_at) = data.pairing_codes.remove(pos);
+        Ok((expire_at >= now).then_some(owner))
+    }
+
+    async fn create_device_session(
+        &self,
+        sid: &str,
+        owner: &str,
+        browser: &str,
+        os: &str,
+    ) -> Result<()> {
+        let mut data = self.lock()?;
+        let now = now_timestamp();
+        data.device_sessions.push((
+            owner.to_string(),
+            DeviceSessionRecord {
+                sid: sid.to_string(),
+                browser: browser.to_string(),
+                os: os.to_string(),
+                create_at: now.clone(),
+                last_seen_at: now,
+            },
+        ));
+        Ok(())
+    }
+
+    async fn touch_device_session(&self, sid: &str) -> Result<Option<String>> {
+        let mut data = self.lock()?;
+        let found = data.device_sessions.iter_mut().find(|(_, s)| s.sid == sid);
+        Ok(found.map(|(owner, s)| {
+            s.last_seen_at = now_timestamp();
+            owner.clone()
+        }))
+    }
+
+    async fn set_device_session_owner(&self, sid: &str, owner: &str) -> Result<()> {
+        let mut data = self.lock()?;
+        for (o, _) in data
+            .device_sessions
+            .iter_mut()
+            .filter(|(_, s)| s.sid == sid)
+        {
+            *o = owner.to_string();
+        }
+        Ok(())
+    }
+
+    async fn list_device_sessions(&self, owner: &str) -> Result<Vec<DeviceSessionRecord>> {
+        let data = self.lock()?;
+        let mut sessions: Vec<_> = data
+            .device_sessions
+            .iter()
+            .rev()
+            .filter(|(o, _)| o == owner)
+            .map(|(_, s)| s.clone())
+            .collect();
+        sessions.sort_by(|a, b| b.last_seen_at.cmp(&a.last_seen_at));
+        Ok(sessions)
+    }
+
+    async fn delete_device_session(&self, owner: &str, sid: &str) -> Result<bool> {
+        let mut data = self.lock()?;
+        let len = data.device_sessions.len();
+        data.device_sessions
+            .retain(|(o, s)| !(o == owner && s.sid == sid));
+        Ok(data.device_sessions.len() < len)
+    }
+
+    async fn delete_device_sessions(&self, owner: &str, keep_sid: Option<&str>) -> Result<u64> {
+        let mut data = self.lock()?;
+        let len = data.device_sessions.len();
+        data.device_sessions
+            .retain(|(o, s)| !(o == owner && Some(s.sid.as_str()) != keep_sid));
+        Ok((len - data.device_sessions.len()) as u64)
+    }
+
+    async fn delete_stale_device_sessions(&self, before: &str) -> Result<u64> {
+        let mut data = self.lock()?;
+        let len = data.device_sessions.len();
+        data.device_sessions
+            .retain(|(_, s)| s.last_seen_at.as_str() >= before);
+        Ok((len - data.device_sessions.len()) as u64)
+    }
+
+    async fn delete_abandoned_cats(&self, before: &str) -> Result<u64> {
+        let mut data = self.lock()?;
+        let is_alive = |data: &MemoryData, owner: &str| {
+            data.device_sessions.iter().any(|(o, _)| o == owner)
+                || data.users.iter().any(|a| a.owner == owner)
+                || data
+                    .cats
+                    .iter()
+                    .any(|cat| cat.bicmid == owner && cat.create_at.as_str() >= before)
+        };
+        let abandoned: Vec<String> = data
+            .cats
+            .iter()
+            .filter(|cat| !is_alive(&data, &cat.bicmid))
+            .map(|cat| cat.bicmid.clone())
+            .collect();
+        let len = data.cats.len();
+        data.cats.retain(|cat| !abandoned.contains(&cat.bicmid));
+        Ok((len - data.cats.len()) as u64)
+    }
+
+    async fn delete_orphans(&self) -> Result<(u64, u64)> {
+        let mut data = self.lock()?;
+        let MemoryData {
+            users,
+            device_sessions,
+            preferences,
+            ..
+        } = &mut *data;
+        preferences.retain(|(owner, _, _)| {
+            device_sessions.iter().any(|(o, _)| o == owner)
+                || users.iter().any(|u| u.owner == *owner)
+        });
+        // The owners and the origins are not kept apart from the cats
+        Ok((0, 0))
+    }
+
+    async fn optimize(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn set_image_hash(&self, url: &str, hash: &str) -> Result<()> {
+        let mut data = self.lock()?;
+        for cat in data.cats.iter_mut().filter(|cat| cat.cat.url == url) {
+            cat.image_hash = Some(hash.to_string());
+        }
+        Ok(())
+    }
+
+    async fn export_cats(&self, bicmid: &str, after_id: i64, limit: i64) -> Result<Vec<CatRecord>> {
+        let limit: usize = limit.try_into()?;
+        let data = self.lock()?;
+        let cats = data
+            .cats
+            .iter()
+            .filter(|cat| cat.bicmid == bicmid && cat.id > after_id && cat.deleted_at.is_none())
+            .take(limit)
+            .map(|cat| CatRecord {
+                id: cat.id,
+                url: cat.cat.url.clone(),
+                create_at: cat.create_at.clone(),
+                image_hash: cat.image_hash.clone(),
+            })
+            .collect();
+        Ok(cats)
+    }
+
+    async fn select_cats(&self, bicmid: &str, ids: &[i64]) -> Result<Vec<CatRecord>> {
+        let data = self.lock()?;
+        let cats = data
+            .cats
+            .iter()
+            .filter(|cat| cat.bicmid == bicmid && ids.contains(&cat.id) && cat.deleted_at.is_none())
+            .map(|cat| CatRecord {
+                id: cat.id,
+                url: cat.cat.url.clone(),
+                create_at: cat.create_at.clone(),
+                image_hash: cat.image_hash.clone(),
+            })
+            .collect();
+        Ok(cats)
+    }
+
+    async fn tag_cats(&self, bicmid: &str, ids: &[i64], tag: &str, is_tagged: bool) -> Result<u64> {
+        let mut data = self.lock()?;
+        let mut count = 0;
+        for cat in data
+            .cats
+            .iter_mut()
+            .filter(|cat| cat.bicmid == bicmid && ids.contains(&cat.id) && cat.deleted_at.is_none())
+        {
+            if edit_tags(&mut cat.tags, tag, is_tagged) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    async fn move_cats(&self, bicmid: &str, ids: &[i64], album: Option<&str>) -> Result<u64> {
+        let mut data = self.lock()?;
+        let mut count = 0;
+        for cat in data
+            .cats
+            .iter_mut()
+            .filter(|cat| cat.bicmid == bicmid && ids.contains(&cat.id) && cat.deleted_at.is_none())
+        {
+            cat.album = album.map(str::to_string);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn list_albums(&self, bicmid: &str) -> Result<Vec<String>> {
+        let data = self.lock()?;
+        let mut r: Vec<String> = data
+            .cats
+            .iter()
+            .filter(|cat| cat.bicmid == bicmid && cat.deleted_at.is_none())
+            .filter_map(|cat| cat.album.clone())
+            .collect();
+        r.sort();
+        r.dedup();
+        Ok(r)
+    }
+}
+
+/// Return true, if the cats are the same, as the unique indexes of the database
+fn is_same_cat(a: &NewCat, b: &NewCat) -> bool {
+    a.url == b.url
+        || (a.provider_id.is_some() && a.provider == b.provider && a.provider_id == b.provider_id)
+}
+
+/// Return the current time as the same format as sqlite `CURRENT_TIMESTAMP`
+fn now_timestamp() -> String {
+    db_timestamp(tower_sessions::cookie::time::OffsetDateTime::now_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryCatStore;
+    use crate::backends::store_tests::cat_store_tests;
+
+    async fn open(_name: &str) -> Option<MemoryCatStore> {
+        Some(MemoryCatStore::new())
+    }
+
+    cat_store_tests!(open);
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use futures::future::BoxFuture;
+
+use super::{SqlCatStore, SqlDialect};
+
+/// the cat store on the postgresql database
+pub(crate) type PostgresCatStore = SqlCatStore<sqlx::Postgres>;
+
+impl SqlCatStore<sqlx::Postgres> {
+    /// Connect to the database and apply the pending schema migrations
+    pub(crate) async fn open(db_url: &str) -> Result<Self> {
+        let pool = create_sqlx_pool(db_url).await?;
+        Ok(Self::new(pool))
+    }
+}
+
+impl SqlDialect for sqlx::Postgres {
+    const NOW: &'static str = r#"to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')"#;
+
+    const IDS: &'static str = r#"SELECT value::BIGINT FROM json_array_elements_text($2::JSON)"#;
+
+    const LOCK_ORPHANS: Option<&'static str> =
+        Some(r#"LOCK TABLE Cat, Bicmid, UrlOrigin IN SHARE ROW EXCLUSIVE MODE"#);
+
+    const FOR_UPDATE: &'static str = r#" FOR UPDATE OF Cat"#;
+
+    fn rows_affected(r: &Self::QueryResult) -> u64 {
+        r.rows_affected()
+    }
+
+    fn optimize(pool: &sqlx::Pool<Self>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            // The dead rows are reclaimed by the autovacuum of the server
+            sqlx::query(r#"ANALYZE Cat, Bicmid, UrlOrigin, DeviceSession"#)
+                .execute(pool)
+                .await?;
+            Ok(())
+        })
+    }
+}
+
+pub(crate) async fn create_sqlx_pool(db_url: &str) -> Result<sqlx::postgres::PgPool> {
+    use sqlx::postgres::PgPoolOptions;
+    //
+    let pool = PgPoolOptions::new()
+        .max_connections(10)
+        .connect(db_url)
+        .await?;
+    // Create or upgrade tables to the current schema version
+    let version = super::db_migrate::migrate_postgres(&pool).await?;
+    dioxus_logger::tracing::info!("db schema version: {version}");
+    Ok(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostgresCatStore;
+    use crate::backends::store_tests::{cat_store_tests, postgres_test_pool};
+
+    async fn open(name: &str) -> Option<PostgresCatStore> {
+        let pool = postgres_test_pool(&format!("store_{name}")).await?;
+        crate::backends::db_migrate::migrate_postgres(&pool)
+            .await
+            .unwrap();
+        Some(PostgresCatStore::new(pool))
+    }
+
+    cat_store_tests!(open);
+}
@@ -0,0 +1,874 @@
+use anyhow::Result;
+use futures::future::BoxFuture;
+
+use sqlx::{ColumnIndex, Database, Decode, Encode, Executor, IntoArguments, Row, Type};
+
+use super::{
+    edit_tags, image_url, split_url, CatCursor, CatItem, CatRecord, CatStore, DeviceSessionRecord,
+    NewCat, SaveStatus, TrashItem, UserRecord,
+};
+
+/// the differences of the sql databases, that share the queries of `SqlCatStore`
+///
+/// The queries take the parameters as `$1`, `$2`, ..., that both sqlite and postgresql accept.
+pub(crate) trait SqlDialect: Database {
+    /// the expression of the current time, `YYYY-MM-DD HH:MM:SS` in UTC
+    const NOW: &'static str;
+
+    /// the subquery of the ids in the json array, that is bound to `$2`
+    const IDS: &'static str;
+
+    /// the statement, that locks the tables in `delete_orphans`, if it is needed
+    const LOCK_ORPHANS: Option<&'static str>;
+
+    /// the clause of the select, that locks the rows until the end of the transaction, if it is needed
+    const FOR_UPDATE: &'static str;
+
+    /// Return the count of the rows, that the statement affected
+    fn rows_affected(r: &Self::QueryResult) -> u64;
+
+    /// Reclaim the free space and update the statistics of the database, as needed
+    fn optimize(pool: &sqlx::Pool<Self>) -> BoxFuture<'_, Result<()>>;
+}
+
+/// the cat store on the sql database of `DB`
+pub(crate) struct SqlCatStore<DB: SqlDialect> {
+    pool: sqlx::Pool<DB>,
+}
+
+impl<DB: SqlDialect> SqlCatStore<DB>
+where
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+    for<'q> i64: Encode<'q, DB> + Decode<'q, DB> + Type<DB>,
+    for<'q> String: Encode<'q, DB> + Decode<'q, DB> + Type<DB>,
+    for<'q> &'q str: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<i64>: Encode<'q, DB> + Decode<'q, DB> + Type<DB>,
+    for<'q> Option<String>: Encode<'q, DB> + Decode<'q, DB> + Type<DB>,
+    usize: ColumnIndex<DB::Row>,
+{
+    /// Create the store on the pool, that is migrated already
+    pub(crate) fn new(pool: sqlx::Pool<DB>) -> Self {
+        Self { pool }
+    }
+
+    // The cat of the row of the listing
+    fn cat_item(row: &DB::Row) -> CatItem {
+        let image_hash = row.get::<Option<String>, _>(5);
+        let source_url = format!("{}{}", row.get::<String, _>(1), row.get::<String, _>(2));
+        CatItem {
+            id: row.get::<i64, _>(0),
+            url: match &image_hash {
+                Some(hash) => image_url(hash),
+                None => source_url.clone(),
+            },
+            width: row.get::<Option<i64>, _>(3),
+            height: row.get::<Option<i64>, _>(4),
+            image_hash,
+            source_url,
+            create_at: row.get::<String, _>(6),
+            tags: serde_json::from_str(&row.get::<String, _>(7)).unwrap_or_default(),
+            album: row.get::<Option<String>, _>(8),
+        }
+    }
+
+    // Return the id of the value, that is stored by `sql` if it is not yet
+    async fn get_or_store(tx: &mut sqlx::Transaction<'_, DB>, sql: &str, val: &str) -> Result<i64> {
+        let row = sqlx::query(sql).bind(val).fetch_one(&mut **tx).await?;
+        Ok(row.get(0))
+    }
+}
+
+// The cats older than the cursor, the newest first
+const SQL_LIST_CATS_OLDER: &str = concat!(
+    r#"SELECT Cat.id, UrlOrigin.value, Cat.url_path, Cat.width, Cat.height,"#,
+    r#" Cat.image_hash, Cat.create_at, Cat.tags, Cat.album FROM Cat"#,
+    r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+    r#" INNER JOIN UrlOrigin ON Cat.url_origin_id = UrlOrigin.id"#,
+    r#" WHERE Bicmid.value = $1 AND Cat.id < $2 AND Cat.deleted_at IS NULL"#,
+    r#" ORDER BY Cat.id DESC LIMIT $3"#
+);
+
+// The cats newer than the cursor, the oldest first
+const SQL_LIST_CATS_NEWER: &str = concat!(
+    r#"SELECT Cat.id, UrlOrigin.value, Cat.url_path, Cat.width, Cat.height,"#,
+    r#" Cat.image_hash, Cat.create_at, Cat.tags, Cat.album FROM Cat"#,
+    r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+    r#" INNER JOIN UrlOrigin ON Cat.url_origin_id = UrlOrigin.id"#,
+    r#" WHERE Bicmid.value = $1 AND Cat.id > $2 AND Cat.deleted_at IS NULL"#,
+    r#" ORDER BY Cat.id ASC LIMIT $3"#
+);
+
+// The cats in the trash, the latest deleted first
+const SQL_LIST_TRASH: &str = concat!(
+    r#"SELECT Cat.id, UrlOrigin.value, Cat.url_path, Cat.width, Cat.height,"#,
+    r#" Cat.image_hash, Cat.create_at, Cat.tags, Cat.album, Cat.deleted_at FROM Cat"#,
+    r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+    r#" INNER JOIN UrlOrigin ON Cat.url_origin_id = UrlOrigin.id"#,
+    r#" WHERE Bicmid.value = $1 AND Cat.deleted_at IS NOT NULL"#,
+    r#" ORDER BY Cat.deleted_at DESC, Cat.id DESC"#
+);
+
+#[async_trait::async_trait]
+impl<DB: SqlDialect> CatStore for SqlCatStore<DB>
+where
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+    for<'q> i64: Encode<'q, DB> + Decode<'q, DB> + Type<DB>,
+    for<'q> String: Encode<'q, DB> + Decode<'q, DB> + Type<DB>,
+    for<'q> &'q str: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<i64>: Encode<'q, DB> + Decode<'q, DB> + Type<DB>,
+    for<'q> Option<String>: Encode<'q, DB> + Decode<'q, DB> + Type<DB>,
+    usize: ColumnIndex<DB::Row>,
+{
+    async fn list_cats_page(
+        &self,
+        bicmid: &str,
+        cursor: CatCursor,
+        limit: i64,
+    ) -> Result<Vec<CatItem>> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let (sql, id) = match cursor {
+            CatCursor::First => (SQL_LIST_CATS_OLDER, i64::MAX),
+            CatCursor::Older(id) => (SQL_LIST_CATS_OLDER, id),
+            CatCursor::Newer(id) => (SQL_LIST_CATS_NEWER, id),
+        };
+        let mut cats: Vec<CatItem> = sqlx::query(sql)
+            .bind(bicmid)
+            .bind(id)
+            .bind(limit)
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .map(Self::cat_item)
+            .collect();
+        //
+        tx.commit().await?;
+        // The newer cats are taken in ascending order, next to the cursor
+        if matches!(cursor, CatCursor::Newer(_)) {
+            cats.reverse();
+        }
+        Ok(cats)
+    }
+
+    async fn count_of_cats(&self, bicmid: &str) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(concat!(
+            r#"SELECT count(*) FROM Cat"#,
+            r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+            r#" WHERE Bicmid.value = $1 AND Cat.deleted_at IS NULL"#
+        ))
+        .bind(bicmid)
+        .fetch_one(&mut *tx)
+        .await?
+        .get::<i64, _>(0) as usize;
+        //
+        tx.commit().await?;
+        Ok(r)
+    }
+
+    async fn list_preferences(&self, owner: &str) -> Result<Vec<(String, String)>> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(r#"SELECT name, value FROM Preference WHERE owner = $1 ORDER BY name"#)
+            .bind(owner)
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .map(|row| (row.get::<String, _>(0), row.get::<String, _>(1)))
+            .collect();
+        //
+        tx.commit().await?;
+        Ok(r)
+    }
+
+    async fn set_preference(&self, owner: &str, name: &str, value: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        //
+        sqlx::query(concat!(
+            r#"INSERT INTO Preference (owner, name, value) VALUES ($1, $2, $3)"#,
+            r#" ON CONFLICT (owner, name) DO UPDATE SET value = excluded.value"#
+        ))
+        .bind(owner)
+        .bind(name)
+        .bind(value)
+        .execute(&mut *tx)
+        .await?;
+        //
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_cats(&self, bicmid: &str, ids: &[i64]) -> Result<u64> {
+        let ids_json = serde_json::to_string(ids)?;
+        let sql = format!(
+            concat!(
+                r#"UPDATE Cat SET deleted_at = {now}"#,
+                r#" WHERE deleted_at IS NULL AND id IN ("#,
+                r#" SELECT Cat.id FROM Cat"#,
+                r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+                r#" WHERE Bicmid.value = $1 AND Cat.id IN ({ids})"#,
+                r#" )"#,
+            ),
+            now = DB::NOW,
+            ids = DB::IDS
+        );
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(&sql)
+            .bind(bicmid)
+            .bind(ids_json)
+            .execute(&mut *tx)
+            .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r))
+    }
+
+    async fn list_trash(&self, bicmid: &str) -> Result<Vec<TrashItem>> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(SQL_LIST_TRASH)
+            .bind(bicmid)
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .map(|row| TrashItem {
+                cat: Self::cat_item(row),
+                deleted_at: row.get::<String, _>(9),
+            })
+            .collect();
+        //
+        tx.commit().await?;
+        Ok(r)
+    }
+
+    async fn restore_cats(&self, bicmid: &str, ids: &[i64]) -> Result<u64> {
+        let ids_json = serde_json::to_string(ids)?;
+        let sql = format!(
+            concat!(
+                r#"UPDATE Cat SET deleted_at = NULL"#,
+                r#" WHERE deleted_at IS NOT NULL AND id IN ("#,
+                r#" SELECT Cat.id FROM Cat"#,
+                r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+                r#" WHERE Bicmid.value = $1 AND Cat.id IN ({ids})"#,
+                r#" )"#,
+            ),
+            ids = DB::IDS
+        );
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(&sql)
+            .bind(bicmid)
+            .bind(ids_json)
+            .execute(&mut *tx)
+            .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r))
+    }
+
+    async fn purge_cat(&self, bicmid: &str, id: i64) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(concat!(
+            r#"DELETE FROM Cat"#,
+            r#" WHERE deleted_at IS NOT NULL AND id IN ("#,
+            r#" SELECT Cat.id FROM Cat"#,
+            r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+            r#" WHERE Bicmid.value = $1 AND Cat.id = $2"#,
+            r#" )"#,
+        ))
+        .bind(bicmid)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r) > 0)
+    }
+
+    async fn purge_trash(&self, before: &str) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(r#"DELETE FROM Cat WHERE deleted_at < $1"#)
+            .bind(before)
+            .execute(&mut *tx)
+            .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r))
+    }
+
+    async fn save_cat(&self, bicmid: &str, cat: &NewCat) -> Result<SaveStatus> {
+        let (url_origin, url_path) = split_url(&cat.url);
+        let breeds = serde_json::to_string(&cat.breeds)?;
+        let mut tx = self.pool.begin().await?;
+        //
+        let bicmid_id = Self::get_or_store(&mut tx, SQL_STORE_BICMID, bicmid).await?;
+        let url_origin_id = Self::get_or_store(&mut tx, SQL_STORE_URL_ORIGIN, url_origin).await?;
+        //
+        sqlx::query(concat!(
+            r#"DELETE FROM Cat WHERE bicmid_id = $1 AND deleted_at IS NOT NULL"#,
+            r#" AND ((url_origin_id = $2 AND url_path = $3)"#,
+            r#" OR (provider = $4 AND provider_id = $5))"#,
+        ))
+        .bind(bicmid_id)
+        .bind(url_origin_id)
+        .bind(url_path)
+        .bind(&cat.provider)
+        .bind(&cat.provider_id)
+        .execute(&mut *tx)
+        .await?;
+        let r = sqlx::query(concat!(
+            r#"INSERT INTO Cat"#,
+            r#" (bicmid_id, url_origin_id, url_path,"#,
+            r#" provider, provider_id, width, height, breeds)"#,
+            r#" VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+            r#" ON CONFLICT DO NOTHING"#
+        ))
+        .bind(bicmid_id)
+        .bind(url_origin_id)
+        .bind(url_path)
+        .bind(&cat.provider)
+        .bind(&cat.provider_id)
+        .bind(cat.width)
+        .bind(cat.height)
+        .bind(breeds)
+        .execute(&mut *tx)
+        .await?;
+        //
+        tx.commit().await?;
+        Ok(SaveStatus::from_rows_affected(DB::rows_affected(&r)))
+    }
+
+    async fn rename_owner(&self, from: &str, to: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(concat!(
+            r#"UPDATE Bicmid SET value = $1"#,
+            r#" WHERE value = $2 AND id <> 0"#,
+            r#" AND NOT EXISTS (SELECT 1 FROM Bicmid WHERE value = $3)"#,
+        ))
+        .bind(to)
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r) > 0)
+    }
+
+    async fn merge_owner(&self, from: &str, to: &str) -> Result<u64> {
+        if from == to {
+            return Ok(0);
+        }
+        let mut tx = self.pool.begin().await?;
+        //
+        sqlx::query(r#"UPDATE DeviceSession SET owner = $1 WHERE owner = $2"#)
+            .bind(to)
+            .bind(from)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(concat!(
+            r#"INSERT INTO Preference (owner, name, value)"#,
+            r#" SELECT $1, name, value FROM Preference WHERE owner = $2"#,
+            r#" ON CONFLICT DO NOTHING"#
+        ))
+        .bind(to)
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(r#"DELETE FROM Preference WHERE owner = $1"#)
+            .bind(from)
+            .execute(&mut *tx)
+            .await?;
+        let from_id = sqlx::query(r#"SELECT id FROM Bicmid WHERE value = $1"#)
+            .bind(from)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|row| row.get::<i64, _>(0));
+        let Some(from_id) = from_id else {
+            tx.commit().await?;
+            return Ok(0);
+        };
+        let to_id = Self::get_or_store(&mut tx, SQL_STORE_BICMID, to).await?;
+        //
+        // The live cat wins over the one in the trash
+        sqlx::query(concat!(
+            r#"DELETE FROM Cat WHERE bicmid_id = $1 AND deleted_at IS NOT NULL"#,
+            r#" AND EXISTS (SELECT 1 FROM Cat AS a"#,
+            r#"  WHERE a.bicmid_id = $2 AND a.deleted_at IS NULL"#,
+            r#"  AND ((a.url_origin_id = Cat.url_origin_id AND a.url_path = Cat.url_path)"#,
+            r#"  OR (a.provider = Cat.provider AND a.provider_id = Cat.provider_id)))"#,
+        ))
+        .bind(to_id)
+        .bind(from_id)
+        .execute(&mut *tx)
+        .await?;
+        let r = sqlx::query(concat!(
+            r#"INSERT INTO Cat"#,
+            r#" (bicmid_id, create_at, url_origin_id, url_path,"#,
+            r#" provider, provider_id, width, height, breeds, image_hash, deleted_at,"#,
+            r#" tags, album)"#,
+            r#" SELECT $1, create_at, url_origin_id, url_path,"#,
+            r#" provider, provider_id, width, height, breeds, image_hash, deleted_at,"#,
+            r#" tags, album FROM Cat"#,
+            r#" WHERE bicmid_id = $2 ORDER BY id"#,
+            r#" ON CONFLICT DO NOTHING"#
+        ))
+        .bind(to_id)
+        .bind(from_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(r#"DELETE FROM Cat WHERE bicmid_id = $1"#)
+            .bind(from_id)
+            .execute(&mut *tx)
+            .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r))
+    }
+
+    async fn create_user(&self, user: &UserRecord) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(concat!(
+            r#"INSERT INTO "User" (name, password_hash, owner) VALUES ($1, $2, $3)"#,
+            r#" ON CONFLICT DO NOTHING"#
+        ))
+        .bind(&user.name)
+        .bind(&user.password_hash)
+        .bind(&user.owner)
+        .execute(&mut *tx)
+        .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r) > 0)
+    }
+
+    async fn find_user(&self, name: &str) -> Result<Option<UserRecord>> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let user = sqlx::query(r#"SELECT name, password_hash, owner FROM "User" WHERE name = $1"#)
+            .bind(name)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|row| UserRecord {
+                name: row.get::<String, _>(0),
+                password_hash: row.get::<String, _>(1),
+                owner: row.get::<String, _>(2),
+            });
+        //
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn user_of_owner(&self, owner: &str) -> Result<Option<String>> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let name = sqlx::query(r#"SELECT name FROM "User" WHERE owner = $1"#)
+            .bind(owner)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|row| row.get::<String, _>(0));
+        //
+        tx.commit().await?;
+        Ok(name)
+    }
+
+    async fn create_pairing_code(
+        &self,
+        code: &str,
+        owner: &str,
+        now: i64,
+        expire_at: i64,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        //
+        sqlx::query(r#"DELETE FROM PairingCode WHERE expire_at < $1"#)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(r#"INSERT INTO PairingCode (code, owner, expire_at) VALUES ($1, $2, $3)"#)
+            .bind(code)
+            .bind(owner)
+            .bind(expire_at)
+            .execute(&mut *tx)
+            .await?;
+        //
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn take_pairing_code(&self, code: &str, now: i64) -> Result<Option<String>> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let owner =
+            sqlx::query(r#"DELETE FROM PairingCode WHERE code = $1 RETURNING owner, expire_at"#)
+                .bind(code)
+                .fetch_optional(&mut *tx)
+                .await?
+                .filter(|row| row.get::<i64, _>(1) >= now)
+                .map(|row| row.get::<String, _>(0));
+        //
+        tx.commit().await?;
+        Ok(owner)
+    }
+
+    async fn create_device_session(
+        &self,
+        sid: &str,
+        owner: &str,
+        browser: &str,
+        os: &str,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        //
+        sqlx::query(concat!(
+            r#"INSERT INTO DeviceSession (sid, owner, browser, os)"#,
+            r#" VALUES ($1, $2, $3, $4)"#
+        ))
+        .bind(sid)
+        .bind(owner)
+        .bind(browser)
+        .bind(os)
+        .execute(&mut *tx)
+        .await?;
+        //
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn touch_device_session(&self, sid: &str) -> Result<Option<String>> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let sql = format!(
+            concat!(
+                r#"UPDATE DeviceSession SET last_seen_at = {now}"#,
+                r#" WHERE sid = $1 RETURNING owner"#
+            ),
+            now = DB::NOW
+        );
+        let owner = sqlx::query(&sql)
+            .bind(sid)
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|row| row.get::<String, _>(0));
+        //
+        tx.commit().await?;
+        Ok(owner)
+    }
+
+    async fn set_device_session_owner(&self, sid: &str, owner: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        //
+        sqlx::query(r#"UPDATE DeviceSession SET owner = $1 WHERE sid = $2"#)
+            .bind(owner)
+            .bind(sid)
+            .execute(&mut *tx)
+            .await?;
+        //
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn list_device_sessions(&self, owner: &str) -> Result<Vec<DeviceSessionRecord>> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let rows = sqlx::query(concat!(
+            r#"SELECT sid, browser, os, create_at, last_seen_at FROM DeviceSession"#,
+            r#" WHERE owner = $1 ORDER BY last_seen_at DESC, create_at DESC"#
+        ))
+        .bind(owner)
+        .fetch_all(&mut *tx)
+        .await?;
+        //
+        tx.commit().await?;
+        let r = rows
+            .iter()
+            .map(|row| DeviceSessionRecord {
+                sid: row.get::<String, _>(0),
+                browser: row.get::<String, _>(1),
+                os: row.get::<String, _>(2),
+                create_at: row.get::<String, _>(3),
+                last_seen_at: row.get::<String, _>(4),
+            })
+            .collect();
+        Ok(r)
+    }
+
+    async fn delete_device_session(&self, owner: &str, sid: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(r#"DELETE FROM DeviceSession WHERE owner = $1 AND sid = $2"#)
+            .bind(owner)
+            .bind(sid)
+            .execute(&mut *tx)
+            .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r) > 0)
+    }
+
+    async fn delete_device_sessions(&self, owner: &str, keep_sid: Option<&str>) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(r#"DELETE FROM DeviceSession WHERE owner = $1 AND sid <> $2"#)
+            .bind(owner)
+            .bind(keep_sid.unwrap_or(""))
+            .execute(&mut *tx)
+            .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r))
+    }
+
+    async fn delete_stale_device_sessions(&self, before: &str) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(r#"DELETE FROM DeviceSession WHERE last_seen_at < $1"#)
+            .bind(before)
+            .execute(&mut *tx)
+            .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r))
+    }
+
+    async fn delete_abandoned_cats(&self, before: &str) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(concat!(
+            r#"DELETE FROM Cat WHERE bicmid_id IN ("#,
+            r#" SELECT Bicmid.id FROM Bicmid WHERE Bicmid.id <> 0"#,
+            r#" AND Bicmid.value NOT IN (SELECT owner FROM DeviceSession)"#,
+            r#" AND Bicmid.value NOT IN (SELECT owner FROM "User")"#,
+            r#" AND NOT EXISTS (SELECT 1 FROM Cat AS a"#,
+            r#"  WHERE a.bicmid_id = Bicmid.id AND a.create_at >= $1)"#,
+            r#")"#
+        ))
+        .bind(before)
+        .execute(&mut *tx)
+        .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r))
+    }
+
+    async fn delete_orphans(&self) -> Result<(u64, u64)> {
+        let mut tx = self.pool.begin().await?;
+        //
+        // The saving of cats waits, not to refer to the deleted rows
+        if let Some(sql) = DB::LOCK_ORPHANS {
+            sqlx::query(sql).execute(&mut *tx).await?;
+        }
+        let r1 = sqlx::query(concat!(
+            r#"DELETE FROM Bicmid WHERE id <> 0"#,
+            r#" AND id NOT IN (SELECT bicmid_id FROM Cat)"#
+        ))
+        .execute(&mut *tx)
+        .await?;
+        let r2 = sqlx::query(concat!(
+            r#"DELETE FROM UrlOrigin WHERE id <> 0"#,
+            r#" AND id NOT IN (SELECT url_origin_id FROM Cat)"#
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(concat!(
+            r#"DELETE FROM Preference"#,
+            r#" WHERE owner NOT IN (SELECT owner FROM DeviceSession)"#,
+            r#" AND owner NOT IN (SELECT owner FROM "User")"#
+        ))
+        .execute(&mut *tx)
+        .await?;
+        //
+        tx.commit().await?;
+        Ok((DB::rows_affected(&r1), DB::rows_affected(&r2)))
+    }
+
+    async fn optimize(&self) -> Result<()> {
+        DB::optimize(&self.pool).await
+    }
+
+    async fn set_image_hash(&self, url: &str, hash: &str) -> Result<()> {
+        let (url_origin, url_path) = split_url(url);
+        let mut tx = self.pool.begin().await?;
+        //
+        sqlx::query(concat!(
+            r#"UPDATE Cat SET image_hash = $1"#,
+            r#" WHERE url_origin_id IN (SELECT id FROM UrlOrigin WHERE value = $2)"#,
+            r#" AND url_path = $3"#,
+        ))
+        .bind(hash)
+        .bind(url_origin)
+        .bind(url_path)
+        .execute(&mut *tx)
+        .await?;
+        //
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn export_cats(&self, bicmid: &str, after_id: i64, limit: i64) -> Result<Vec<CatRecord>> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let cats = sqlx::query(concat!(
+            r#"SELECT Cat.id, UrlOrigin.value, Cat.url_path, Cat.create_at,"#,
+            r#" Cat.image_hash FROM Cat"#,
+            r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+            r#" INNER JOIN UrlOrigin ON Cat.url_origin_id = UrlOrigin.id"#,
+            r#" WHERE Bicmid.value = $1 AND Cat.id > $2 AND Cat.deleted_at IS NULL"#,
+            r#" ORDER BY Cat.id ASC LIMIT $3"#
+        ))
+        .bind(bicmid)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|row| CatRecord {
+            id: row.get::<i64, _>(0),
+            url: format!("{}{}", row.get::<String, _>(1), row.get::<String, _>(2)),
+            create_at: row.get::<String, _>(3),
+            image_hash: row.get::<Option<String>, _>(4),
+        })
+        .collect();
+        //
+        tx.commit().await?;
+        Ok(cats)
+    }
+
+    async fn select_cats(&self, bicmid: &str, ids: &[i64]) -> Result<Vec<CatRecord>> {
+        let ids_json = serde_json::to_string(ids)?;
+        let sql = format!(
+            concat!(
+                r#"SELECT Cat.id, UrlOrigin.value, Cat.url_path, Cat.create_at,"#,
+                r#" Cat.image_hash FROM Cat"#,
+                r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+                r#" INNER JOIN UrlOrigin ON Cat.url_origin_id = UrlOrigin.id"#,
+                r#" WHERE Bicmid.value = $1 AND Cat.id IN ({ids}) AND Cat.deleted_at IS NULL"#,
+                r#" ORDER BY Cat.id ASC"#
+            ),
+            ids = DB::IDS
+        );
+        let mut tx = self.pool.begin().await?;
+        //
+        let cats = sqlx::query(&sql)
+            .bind(bicmid)
+            .bind(ids_json)
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .map(|row| CatRecord {
+                id: row.get::<i64, _>(0),
+                url: format!("{}{}", row.get::<String, _>(1), row.get::<String, _>(2)),
+                create_at: row.get::<String, _>(3),
+                image_hash: row.get::<Option<String>, _>(4),
+            })
+            .collect();
+        //
+        tx.commit().await?;
+        Ok(cats)
+    }
+
+    async fn tag_cats(&self, bicmid: &str, ids: &[i64], tag: &str, is_tagged: bool) -> Result<u64> {
+        let ids_json = serde_json::to_string(ids)?;
+        let sql = format!(
+            concat!(
+                r#"SELECT Cat.id, Cat.tags FROM Cat"#,
+                r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+                r#" WHERE Bicmid.value = $1 AND Cat.id IN ({ids}) AND Cat.deleted_at IS NULL"#,
+                r#"{for_update}"#
+            ),
+            ids = DB::IDS,
+            for_update = DB::FOR_UPDATE
+        );
+        let mut tx = self.pool.begin().await?;
+        //
+        let rows = sqlx::query(&sql)
+            .bind(bicmid)
+            .bind(ids_json)
+            .fetch_all(&mut *tx)
+            .await?;
+        let mut count = 0;
+        for row in rows {
+            let mut tags: Vec<String> =
+                serde_json::from_str(&row.get::<String, _>(1)).unwrap_or_default();
+            if !edit_tags(&mut tags, tag, is_tagged) {
+                continue;
+            }
+            sqlx::query(r#"UPDATE Cat SET tags = $1 WHERE id = $2"#)
+                .bind(serde_json::to_string(&tags)?)
+                .bind(row.get::<i64, _>(0))
+                .execute(&mut *tx)
+                .await?;
+            count += 1;
+        }
+        //
+        tx.commit().await?;
+        Ok(count)
+    }
+
+    async fn move_cats(&self, bicmid: &str, ids: &[i64], album: Option<&str>) -> Result<u64> {
+        let ids_json = serde_json::to_string(ids)?;
+        let sql = format!(
+            concat!(
+                r#"UPDATE Cat SET album = $3"#,
+                r#" WHERE deleted_at IS NULL AND id IN ("#,
+                r#" SELECT Cat.id FROM Cat"#,
+                r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+                r#" WHERE Bicmid.value = $1 AND Cat.id IN ({ids})"#,
+                r#" )"#,
+            ),
+            ids = DB::IDS
+        );
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(&sql)
+            .bind(bicmid)
+            .bind(ids_json)
+            .bind(album.map(str::to_string))
+            .execute(&mut *tx)
+            .await?;
+        //
+        tx.commit().await?;
+        Ok(DB::rows_affected(&r))
+    }
+
+    async fn list_albums(&self, bicmid: &str) -> Result<Vec<String>> {
+        let mut tx = self.pool.begin().await?;
+        //
+        let r = sqlx::query(concat!(
+            r#"SELECT DISTINCT Cat.album FROM Cat"#,
+            r#" INNER JOIN Bicmid ON Cat.bicmid_id = Bicmid.id"#,
+            r#" WHERE Bicmid.value = $1 AND Cat.album IS NOT NULL AND Cat.deleted_at IS NULL"#,
+            r#" ORDER BY Cat.album"#
+        ))
+        .bind(bicmid)
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|row| row.get::<String, _>(0))
+        .collect();
+        //
+        tx.commit().await?;
+        Ok(r)
+    }
+}
+
+// The no-op update makes `RETURNING` work on the existing row too
+const SQL_STORE_BICMID: &str = concat!(
+    r#"INSERT INTO Bicmid (value) VALUES ($1)"#,
+    r#" ON CONFLICT (value) DO UPDATE SET value = EXCLUDED.value"#,
+    r#" RETURNING id"#
+);
+
+const SQL_STORE_URL_ORIGIN: &str = concat!(
+    r#"INSERT INTO UrlOrigin (value) VALUES ($1)"#,
+    r#" ON CONFLICT (value) DO UPDATE SET value = EXCLUDED.value"#,
+    r#" RETURNING id"#
+);
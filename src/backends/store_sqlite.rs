@@ -0,0 +1,88 @@
+use anyhow::Result;
+use futures::future::BoxFuture;
+use std::path::PathBuf;
+
+use super::{optimize_sqlite, SqlCatStore, SqlDialect};
+
+/// the cat store on the persisted sqlite database
+pub(crate) type SqliteCatStore = SqlCatStore<sqlx::Sqlite>;
+
+impl SqlCatStore<sqlx::Sqlite> {
+    /// Open the database and apply the pending schema migrations
+    pub(crate) async fn open() -> Result<Self> {
+        let pool = create_sqlx_pool().await?;
+        Ok(Self::new(pool))
+    }
+}
+
+impl SqlDialect for sqlx::Sqlite {
+    const NOW: &'static str = r#"CURRENT_TIMESTAMP"#;
+
+    const IDS: &'static str = r#"SELECT value FROM json_each($2)"#;
+
+    // The writes of sqlite are serialized already
+    const LOCK_ORPHANS: Option<&'static str> = None;
+
+    const FOR_UPDATE: &'static str = "";
+
+    fn rows_affected(r: &Self::QueryResult) -> u64 {
+        r.rows_affected()
+    }
+
+    fn optimize(pool: &sqlx::Pool<Self>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            optimize_sqlite(pool).await?;
+            Ok(())
+        })
+    }
+}
+
+async fn create_sqlx_pool() -> Result<sqlx::sqlite::SqlitePool> {
+    use sqlx::sqlite::SqliteConnectOptions;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::str::FromStr;
+    //
+    let db_path = get_db_path_();
+    let sq_uri = format!("sqlite://{}", db_path.display());
+    // Open the database from the persisted "cattongue.sqlite3" file
+    let opts = SqliteConnectOptions::from_str(&sq_uri)?.create_if_missing(true);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(opts)
+        .await?;
+    // Create or upgrade tables to the current schema version
+    let version = super::db_migrate::migrate(&pool).await?;
+    dioxus_logger::tracing::info!("db schema version: {version}");
+    Ok(pool)
+}
+
+fn get_db_path_() -> PathBuf {
+    let db = &super::config().database;
+    match &db.path {
+        Some(path) => path.clone(),
+        None => super::data_base_dir().join(db.file.as_deref().unwrap_or("cattongue.sqlite3")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqliteCatStore;
+    use crate::backends::store_tests::cat_store_tests;
+
+    // The in-memory database lives while the only connection of the pool is open
+    async fn open(_name: &str) -> Option<SqliteCatStore> {
+        use sqlx::sqlite::SqlitePoolOptions;
+        //
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::backends::db_migrate::migrate(&pool).await.unwrap();
+        Some(SqliteCatStore::new(pool))
+    }
+
+    cat_store_tests!(open);
+}
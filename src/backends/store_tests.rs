@@ -0,0 +1,323 @@
+//! the conformance tests, that every `CatStore` passes
+//!
+//! Each store runs them by `cat_store_tests!()` in the tests of the module of it.
+
+use super::{CatCursor, CatStore, NewCat, SaveStatus};
+
+/// Generate the conformance tests of the store, that `$open(name)` opens
+///
+/// `$open` returns `None`, if the store is not available for the tests,
+/// e.g. the database url is not given, then the tests pass without checking.
+macro_rules! cat_store_tests {
+    ($open:ident) => {
+        cat_store_tests!($open, save_cat, list_cats_page, trash, merge_owner, tags_and_albums);
+    };
+    ($open:ident, $($check:ident),*) => {
+        $(
+            #[tokio::test]
+            async fn $check() {
+                let Some(store) = $open(stringify!($check)).await else {
+                    return;
+                };
+                crate::backends::store_tests::$check(&store).await;
+            }
+        )*
+    };
+}
+pub(crate) use cat_store_tests;
+
+/// Connect to the empty schema of `name` in the postgresql database for the tests
+///
+/// The database is given by `CATTONGUE_TEST_DATABASE_URL`, e.g. `make test-postgres`.
+/// Return `None`, if it is not set.
+#[cfg(feature = "postgres")]
+pub(crate) async fn postgres_test_pool(name: &str) -> Option<sqlx::postgres::PgPool> {
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+    use std::str::FromStr;
+    //
+    let Ok(url) = std::env::var("CATTONGUE_TEST_DATABASE_URL") else {
+        eprintln!("skipped: CATTONGUE_TEST_DATABASE_URL is not set");
+        return None;
+    };
+    // Each test has the schema of it, that is created again on each run
+    let schema = format!("cattongue_test_{name}");
+    let admin = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&url)
+        .await
+        .unwrap();
+    let sql = format!("DROP SCHEMA IF EXISTS {schema} CASCADE; CREATE SCHEMA {schema};");
+    sqlx::raw_sql(&sql).execute(&admin).await.unwrap();
+    admin.close().await;
+    let opts = PgConnectOptions::from_str(&url)
+        .unwrap()
+        .options([("search_path", schema.as_str())]);
+    let pool = PgPoolOptions::new()
+        .max_connections(2)
+        .connect_with(opts)
+        .await
+        .unwrap();
+    Some(pool)
+}
+
+fn cat(url: &str) -> NewCat {
+    NewCat::from_url(url)
+}
+
+fn provider_cat(url: &str, provider_id: &str) -> NewCat {
+    NewCat {
+        url: url.to_string(),
+        provider: "thecatapi".to_string(),
+        provider_id: Some(provider_id.to_string()),
+        ..Default::default()
+    }
+}
+
+// The ids of the cats of `owner`, the newest first
+async fn ids_of(store: &dyn CatStore, owner: &str) -> Vec<i64> {
+    let cats = store.list_cats_page(owner, CatCursor::First, 100).await;
+    cats.unwrap().iter().map(|cat| cat.id).collect()
+}
+
+// The source urls of the cats of `owner`, in ascending order
+async fn urls_of(store: &dyn CatStore, owner: &str) -> Vec<String> {
+    let cats = store.list_cats_page(owner, CatCursor::First, 100).await;
+    let mut urls: Vec<String> = cats.unwrap().into_iter().map(|c| c.source_url).collect();
+    urls.sort();
+    urls
+}
+
+pub(crate) async fn save_cat(store: &dyn CatStore) {
+    let url = "https://cdn2.thecatapi.com/images/a1.jpg";
+    let r = store.save_cat("alice", &cat(url)).await.unwrap();
+    assert_eq!(r, SaveStatus::Saved);
+    let r = store.save_cat("alice", &cat(url)).await.unwrap();
+    assert_eq!(r, SaveStatus::AlreadySaved);
+    // The same image of the provider at the other url
+    let r = store
+        .save_cat("alice", &provider_cat(url, "a1"))
+        .await
+        .unwrap();
+    assert_eq!(r, SaveStatus::AlreadySaved);
+    let other = "https://cdn2.thecatapi.com/images/a1.png";
+    let r = store
+        .save_cat("alice", &provider_cat(other, "b2"))
+        .await
+        .unwrap();
+    assert_eq!(r, SaveStatus::Saved);
+    let r = store
+        .save_cat("alice", &provider_cat(other, "b2"))
+        .await
+        .unwrap();
+    assert_eq!(r, SaveStatus::AlreadySaved);
+    // The cat is unique per owner
+    let r = store.save_cat("bob", &cat(url)).await.unwrap();
+    assert_eq!(r, SaveStatus::Saved);
+    // The url relative to the server, e.g. the `local` provider
+    let r = store
+        .save_cat("bob", &cat("/api/v1/local/c3.png"))
+        .await
+        .unwrap();
+    assert_eq!(r, SaveStatus::Saved);
+    //
+    assert_eq!(store.count_of_cats("alice").await.unwrap(), 2);
+    assert_eq!(store.count_of_cats("bob").await.unwrap(), 2);
+    assert_eq!(store.count_of_cats("carol").await.unwrap(), 0);
+    assert_eq!(urls_of(store, "alice").await, [url, other]);
+    assert_eq!(urls_of(store, "bob").await, ["/api/v1/local/c3.png", url]);
+    let cats = store.list_cats_page("alice", CatCursor::First, 100).await;
+    for cat in cats.unwrap() {
+        assert_eq!(cat.url, cat.source_url);
+        assert_eq!(cat.create_at.len(), "YYYY-MM-DD HH:MM:SS".len());
+    }
+    // The export has the hash of the cached image
+    let hash = "0".repeat(64);
+    store.set_image_hash(url, &hash).await.unwrap();
+    let cats = store.export_cats("alice", 0, 100).await.unwrap();
+    let hashes: Vec<_> = cats.iter().map(|cat| cat.image_hash.as_deref()).collect();
+    assert_eq!(hashes, [Some(hash.as_str()), None]);
+}
+
+pub(crate) async fn list_cats_page(store: &dyn CatStore) {
+    for i in 0..5 {
+        let url = format!("https://example.com/{i}.png");
+        store.save_cat("alice", &cat(&url)).await.unwrap();
+    }
+    let all = ids_of(store, "alice").await;
+    assert_eq!(all.len(), 5);
+    assert!(all.windows(2).all(|w| w[0] > w[1]), "{all:?}");
+    //
+    let page = |cursor: CatCursor| async move {
+        let cats = store.list_cats_page("alice", cursor, 2).await.unwrap();
+        cats.iter().map(|cat| cat.id).collect::<Vec<_>>()
+    };
+    assert_eq!(page(CatCursor::First).await, all[0..2]);
+    assert_eq!(page(CatCursor::Older(all[1])).await, all[2..4]);
+    assert_eq!(page(CatCursor::Older(all[3])).await, all[4..]);
+    assert!(page(CatCursor::Older(all[4])).await.is_empty());
+    // The newer cats next to the cursor, the newest first
+    assert_eq!(page(CatCursor::Newer(all[4])).await, all[2..4]);
+    assert_eq!(page(CatCursor::Newer(all[2])).await, all[0..2]);
+    assert!(page(CatCursor::Newer(all[0])).await.is_empty());
+    // The cats of the other owner are never listed
+    assert!(ids_of(store, "bob").await.is_empty());
+}
+
+pub(crate) async fn trash(store: &dyn CatStore) {
+    for i in 0..3 {
+        let url = format!("https://example.com/{i}.png");
+        store.save_cat("alice", &cat(&url)).await.unwrap();
+    }
+    store
+        .save_cat("bob", &cat("https://example.com/0.png"))
+        .await
+        .unwrap();
+    let [c2, c1, c0] = ids_of(store, "alice").await[..] else {
+        panic!("3 cats are expected");
+    };
+    let bob = ids_of(store, "bob").await[0];
+    // The cat of the other owner is not deleted
+    assert_eq!(store.delete_cats("alice", &[c2, c1, bob]).await.unwrap(), 2);
+    assert_eq!(store.delete_cats("alice", &[c2]).await.unwrap(), 0);
+    assert_eq!(ids_of(store, "alice").await, [c0]);
+    assert_eq!(ids_of(store, "bob").await, [bob]);
+    assert_eq!(store.count_of_cats("alice").await.unwrap(), 1);
+    let trash = store.list_trash("alice").await.unwrap();
+    let mut trashed: Vec<i64> = trash.iter().map(|item| item.cat.id).collect();
+    trashed.sort();
+    assert_eq!(trashed, [c1, c2]);
+    assert!(trash.iter().all(|item| item.deleted_at.len() == 19));
+    assert_eq!(store.list_trash("bob").await.unwrap(), []);
+    // The cats in the trash are never exported
+    let ids = |cats: Vec<super::CatRecord>| cats.iter().map(|c| c.id).collect::<Vec<_>>();
+    assert_eq!(ids(store.export_cats("alice", 0, 100).await.unwrap()), [c0]);
+    let selected = store.select_cats("alice", &[c2, c1, c0, bob]).await;
+    assert_eq!(ids(selected.unwrap()), [c0]);
+    //
+    assert_eq!(
+        store.restore_cats("alice", &[c1, c0, bob]).await.unwrap(),
+        1
+    );
+    assert_eq!(ids_of(store, "alice").await, [c1, c0]);
+    // Only the cat in the trash is purged
+    assert!(!store.purge_cat("alice", c1).await.unwrap());
+    assert!(!store.purge_cat("bob", c2).await.unwrap());
+    assert!(store.purge_cat("alice", c2).await.unwrap());
+    assert_eq!(store.list_trash("alice").await.unwrap(), []);
+    //
+    store.delete_cats("alice", &[c1]).await.unwrap();
+    assert_eq!(store.purge_trash("2000-01-01 00:00:00").await.unwrap(), 0);
+    assert_eq!(store.purge_trash("9999-12-31 23:59:59").await.unwrap(), 1);
+    assert_eq!(store.list_trash("alice").await.unwrap(), []);
+    // The cat in the trash is replaced by the saved again one
+    store.delete_cats("alice", &[c0]).await.unwrap();
+    let r = store
+        .save_cat("alice", &cat("https://example.com/0.png"))
+        .await;
+    assert_eq!(r.unwrap(), SaveStatus::Saved);
+    assert_eq!(store.list_trash("alice").await.unwrap(), []);
+    let ids = ids_of(store, "alice").await;
+    assert_eq!(ids.len(), 1);
+    assert_ne!(ids[0], c0);
+}
+
+pub(crate) async fn merge_owner(store: &dyn CatStore) {
+    let [x, y, z] = ["x", "y", "z"].map(|s| format!("https://example.com/{s}.png"));
+    // `to` has `x` in the trash and `y`, `from` has `x`, `y` in the trash and `z`
+    store.save_cat("to", &cat(&x)).await.unwrap();
+    store.save_cat("to", &cat(&y)).await.unwrap();
+    let to_x = ids_of(store, "to").await[1];
+    store.delete_cats("to", &[to_x]).await.unwrap();
+    for url in [&x, &y, &z] {
+        store.save_cat("from", &cat(url)).await.unwrap();
+    }
+    let from_y = ids_of(store, "from").await[1];
+    store.delete_cats("from", &[from_y]).await.unwrap();
+    store
+        .set_preference("from", "layout", "grid")
+        .await
+        .unwrap();
+    store
+        .set_preference("from", "scroll", "true")
+        .await
+        .unwrap();
+    store.set_preference("to", "layout", "list").await.unwrap();
+    store
+        .create_device_session("sid-from", "from", "Firefox 130.0", "Linux")
+        .await
+        .unwrap();
+    //
+    assert_eq!(store.merge_owner("from", "from").await.unwrap(), 0);
+    // The live `x` wins over the one in the trash, the live `y` of `to` is kept
+    assert_eq!(store.merge_owner("from", "to").await.unwrap(), 2);
+    assert_eq!(urls_of(store, "to").await, [x, y, z]);
+    assert_eq!(store.list_trash("to").await.unwrap(), []);
+    assert_eq!(store.count_of_cats("from").await.unwrap(), 0);
+    assert_eq!(store.list_trash("from").await.unwrap(), []);
+    // The preferences that `to` already has are kept
+    let preferences = store.list_preferences("to").await.unwrap();
+    let expected = [("layout", "list"), ("scroll", "true")].map(|(n, v)| (n.into(), v.into()));
+    assert_eq!(preferences, expected);
+    assert_eq!(store.list_preferences("from").await.unwrap(), []);
+    let sessions = store.list_device_sessions("to").await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].sid, "sid-from");
+    assert_eq!(store.list_device_sessions("from").await.unwrap(), []);
+    // Nothing is moved from the owner without cats
+    assert_eq!(store.merge_owner("nobody", "to").await.unwrap(), 0);
+    assert_eq!(store.count_of_cats("to").await.unwrap(), 3);
+}
+
+pub(crate) async fn tags_and_albums(store: &dyn CatStore) {
+    let [x, y, z] = ["x", "y", "z"].map(|s| format!("https://example.com/{s}.png"));
+    for url in [&x, &y, &z] {
+        store.save_cat("owner", &cat(url)).await.unwrap();
+    }
+    store.save_cat("other", &cat(&x)).await.unwrap();
+    let other_x = ids_of(store, "other").await[0];
+    let [cz, cy, cx] = ids_of(store, "owner").await[..] else {
+        panic!("3 cats");
+    };
+    store.delete_cats("owner", &[cz]).await.unwrap();
+    // The cats in the trash and of the other owner are not changed
+    let ids = [cx, cy, cz, other_x];
+    assert_eq!(store.tag_cats("owner", &ids, "b", true).await.unwrap(), 2);
+    assert_eq!(store.tag_cats("owner", &[cx], "a", true).await.unwrap(), 1);
+    assert_eq!(store.tag_cats("owner", &[cx], "a", true).await.unwrap(), 0);
+    assert_eq!(
+        store.move_cats("owner", &ids, Some("album")).await.unwrap(),
+        2
+    );
+    let tags_of = |owner: &'static str| async move {
+        let cats = store.list_cats_page(owner, CatCursor::First, 100).await;
+        let cats = cats.unwrap().into_iter();
+        cats.map(|cat| (cat.tags, cat.album)).collect::<Vec<_>>()
+    };
+    let album = Some("album".to_string());
+    let expected = [
+        (vec!["b".to_string()], album.clone()),
+        (vec!["a".into(), "b".into()], album),
+    ];
+    assert_eq!(tags_of("owner").await, expected);
+    assert_eq!(tags_of("other").await, [(vec![], None)]);
+    assert_eq!(store.list_albums("owner").await.unwrap(), ["album"]);
+    assert_eq!(
+        store.list_albums("other").await.unwrap(),
+        Vec::<String>::new()
+    );
+    // The tags and the album are moved with the cats
+    assert_eq!(
+        store
+            .tag_cats("owner", &[cx, cy], "b", false)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(store.move_cats("owner", &[cy], None).await.unwrap(), 1);
+    store.merge_owner("owner", "merged").await.unwrap();
+    let album = Some("album".to_string());
+    assert_eq!(
+        tags_of("merged").await,
+        [(vec![], None), (vec!["a".to_string()], album)]
+    );
+}
@@ -0,0 +1,85 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+
+#[cfg(feature = "server")]
+use super::{backend_delay, check_bulk_ids, get_owner_from_session, store};
+
+/// Add the tag to the cats of `ids` in a transaction
+///
+/// Return the count of the cats, that did not have the tag.
+#[post("/api/v1/cats/tag", session: tower_sessions::Session)]
+pub async fn tag_cats(ids: Vec<i64>, tag: String) -> Result<u64> {
+    let owner = get_owner_from_session(&session).await?;
+    check_bulk_ids(&ids)?;
+    let tag = check_label("tag", &tag)?;
+    let r = store().tag_cats(&owner, &ids, &tag, true).await?;
+    //
+    backend_delay().await;
+    //
+    Ok(r)
+}
+
+/// Remove the tag from the cats of `ids` in a transaction
+///
+/// Return the count of the cats, that had the tag.
+#[post("/api/v1/cats/untag", session: tower_sessions::Session)]
+pub async fn untag_cats(ids: Vec<i64>, tag: String) -> Result<u64> {
+    let owner = get_owner_from_session(&session).await?;
+    check_bulk_ids(&ids)?;
+    let tag = check_label("tag", &tag)?;
+    let r = store().tag_cats(&owner, &ids, &tag, false).await?;
+    //
+    backend_delay().await;
+    //
+    Ok(r)
+}
+
+/// Move the cats of `ids` to the album in a transaction
+///
+/// The empty `album` moves them out of the album. Return the count of moved cats.
+#[post("/api/v1/cats/move", session: tower_sessions::Session)]
+pub async fn move_cats(ids: Vec<i64>, album: String) -> Result<u64> {
+    let owner = get_owner_from_session(&session).await?;
+    check_bulk_ids(&ids)?;
+    let album = match album.trim() {
+        "" => None,
+        name => Some(check_label("album", name)?),
+    };
+    let r = store().move_cats(&owner, &ids, album.as_deref()).await?;
+    //
+    backend_delay().await;
+    //
+    Ok(r)
+}
+
+/// Return the names of the albums of the owner of this session, in ascending order
+#[get("/api/v1/albums", session: tower_sessions::Session)]
+pub async fn list_albums() -> Result<Vec<String>> {
+    let owner = get_owner_from_session(&session).await?;
+    store().list_albums(&owner).await
+}
+
+/// Return the trimmed name of the tag or the album, or an error if it is invalid
+#[cfg(feature = "server")]
+fn check_label(kind: &str, name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 64 || name.chars().any(char::is_control) {
+        return Err(anyhow::anyhow!("the {kind} must be 1 to 64 characters"));
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+#[cfg(feature = "server")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_is_checked() {
+        assert_eq!(check_label("tag", " sleepy ").unwrap(), "sleepy");
+        assert!(check_label("tag", " ").is_err());
+        assert!(check_label("tag", "a\nb").is_err());
+        assert!(check_label("album", &"a".repeat(64)).is_ok());
+        assert!(check_label("album", &"a".repeat(65)).is_err());
+    }
+}
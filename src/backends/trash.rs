@@ -0,0 +1,75 @@
+use anyhow::Result;
+use dioxus::prelude::*;
+
+use super::CatItem;
+
+#[cfg(feature = "server")]
+use super::{backend_delay, check_bulk_ids, get_owner_from_session, store};
+
+/// the deleted cat, that is kept in the trash until it is purged
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TrashItem {
+    pub cat: CatItem,
+    /// the time of the deletion, `YYYY-MM-DD HH:MM:SS` in UTC
+    pub deleted_at: String,
+}
+
+/// the trash of the owner of this session
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TrashList {
+    pub items: Vec<TrashItem>,
+    /// the days, that the cats are kept in the trash, `0` if they are kept forever
+    pub purge_days: i64,
+}
+
+/// Return the cats in the trash of the owner of this session
+#[get("/api/v1/trash", session: tower_sessions::Session)]
+pub async fn list_trash() -> Result<TrashList> {
+    let owner = get_owner_from_session(&session).await?;
+    let items = store().list_trash(&owner).await?;
+    //
+    backend_delay().await;
+    //
+    Ok(TrashList {
+        items,
+        purge_days: super::config().maintenance.trash_days,
+    })
+}
+
+/// Restore the cat from the trash
+///
+/// Return false, if the cat is not in the trash, e.g. it is purged.
+#[post("/api/v1/trash/{id}", session: tower_sessions::Session)]
+pub async fn restore_cat(id: i64) -> Result<bool> {
+    let owner = get_owner_from_session(&session).await?;
+    let r = store().restore_cats(&owner, &[id]).await?;
+    //
+    backend_delay().await;
+    //
+    Ok(r > 0)
+}
+
+/// Restore the cats of `ids` from the trash in a transaction
+///
+/// Return the count of restored cats.
+#[post("/api/v1/trash/restore", session: tower_sessions::Session)]
+pub async fn restore_cats(ids: Vec<i64>) -> Result<u64> {
+    let owner = get_owner_from_session(&session).await?;
+    check_bulk_ids(&ids)?;
+    let r = store().restore_cats(&owner, &ids).await?;
+    //
+    backend_delay().await;
+    //
+    Ok(r)
+}
+
+/// Delete the cat in the trash permanently
+#[delete("/api/v1/trash/{id}", session: tower_sessions::Session)]
+pub async fn purge_cat(id: i64) -> Result<bool> {
+    let owner = get_owner_from_session(&session).await?;
+    let r = store().purge_cat(&owner, id).await?;
+    //
+    backend_delay().await;
+    //
+    Ok(r)
+}
@@ -2,6 +2,8 @@ use browserinfocm::browserinfo::{BroInfo, Browser};
 use browserinfocm::BrowserInfoCm;
 use dioxus::prelude::*;
 
+use crate::backends::Identity;
+
 /// the component of browser information
 #[component]
 pub fn Info() -> Element {
@@ -36,8 +38,25 @@ pub fn Info() -> Element {
                 }
                 bicmid
             };
-            let r = crate::backends::check_session(bicmid).await.unwrap();
-            check_session_sig.set(r);
+            let token = load_identity_token().await;
+            // The browser is shown in the list of sessions
+            let browser = browser_sig.read().clone();
+            match crate::backends::check_session(bicmid, token, browser).await {
+                Ok(r) => {
+                    keep_identity(r.identity).await;
+                    if r.is_renewed {
+                        // The shown cats may be of the rejected session
+                        dioxus_logger::tracing::info!("the session is re-established");
+                        crate::offline::clear_cache().await;
+                        let _ = document::eval("location.reload();").await;
+                    }
+                    check_session_sig.set(true);
+                }
+                Err(e) => {
+                    dioxus_logger::tracing::info!("check_session: {e}");
+                    check_session_sig.set(false);
+                }
+            }
         });
     });
 
@@ -51,3 +70,89 @@ pub fn Info() -> Element {
         {}
     }
 }
+
+/// Keep the identity, that is returned by `check_session()`, `login()` or `link_device()`
+///
+/// The changes on this device are sent as the owner of them.
+pub(crate) async fn keep_identity(identity: Identity) {
+    if let Err(e) = save_identity_token(&identity.token).await {
+        dioxus_logger::tracing::warn!("Failed to keep the identity token: {e}");
+    }
+    crate::offline::set_owner(identity.owner_key).await;
+}
+
+/// Forget the identity, at the logout
+///
+/// The cached favorites and the pending changes of the identity are forgotten too.
+pub(crate) async fn clear_identity() {
+    if let Err(e) = remove_identity_token().await {
+        dioxus_logger::tracing::warn!("Failed to forget the identity token: {e}");
+    }
+    crate::offline::clear().await;
+}
+
+// The identity token is kept in `identity.token` of the data directory of the app,
+// over the restart of the app that forgets the session cookie
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+fn identity_token_path() -> Option<std::path::PathBuf> {
+    crate::settings::app_data_dir().map(|dir| dir.join("identity.token"))
+}
+
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+async fn load_identity_token() -> Option<String> {
+    let token = std::fs::read_to_string(identity_token_path()?).ok()?;
+    Some(token.trim().to_string())
+}
+
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+async fn save_identity_token(token: &str) -> anyhow::Result<()> {
+    let Some(path) = identity_token_path() else {
+        return Err(anyhow::anyhow!("could NOT get the data directory"));
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut opts, 0o600);
+    std::io::Write::write_all(&mut opts.open(&path)?, token.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+async fn remove_identity_token() -> anyhow::Result<()> {
+    match identity_token_path().map(std::fs::remove_file) {
+        Some(Err(e)) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+// The identity token is kept in the local storage, over the expiry of the session
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+const IDENTITY_TOKEN_KEY: &str = "cattongue.identity";
+
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+async fn load_identity_token() -> Option<String> {
+    let js = format!("return localStorage.getItem('{IDENTITY_TOKEN_KEY}');");
+    match document::eval(&js).await {
+        Ok(v) => v.as_str().map(|s| s.to_string()),
+        Err(_e) => None,
+    }
+}
+
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+async fn save_identity_token(token: &str) -> anyhow::Result<()> {
+    let js = format!("localStorage.setItem('{IDENTITY_TOKEN_KEY}', await dioxus.recv());");
+    let eval = document::eval(&js);
+    eval.send(token)?;
+    eval.await?;
+    Ok(())
+}
+
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+async fn remove_identity_token() -> anyhow::Result<()> {
+    let js = format!("localStorage.removeItem('{IDENTITY_TOKEN_KEY}');");
+    document::eval(&js).await?;
+    Ok(())
+}
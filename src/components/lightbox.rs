@@ -0,0 +1,110 @@
+use dioxus::prelude::*;
+
+use crate::backends::{image_src, CatItem};
+
+// the distance of the swipe to step, in pixels
+const SWIPE_DISTANCE: f64 = 50.0;
+
+/// the component of the full screen viewer of a cat
+///
+/// The arrow keys and the swipe step to the previous or next cat,
+/// and the escape key closes the viewer.
+#[component]
+pub(crate) fn Lightbox(
+    cat: CatItem,
+    has_prev: bool,
+    has_next: bool,
+    on_prev: EventHandler<()>,
+    on_next: EventHandler<()>,
+    on_close: EventHandler<()>,
+    on_delete: EventHandler<i64>,
+) -> Element {
+    let mut touch_start = use_signal(|| None::<f64>);
+    let id = cat.id;
+    let src = image_src(&cat.url);
+    let saved = if cat.create_at.is_empty() {
+        "not saved yet".to_string()
+    } else {
+        format!("saved at {} UTC", cat.create_at)
+    };
+    rsx! {
+        MyStyle {}
+        div {
+            class: "lightbox",
+            tabindex: 0,
+            onmounted: move |evt| async move {
+                let _ = evt.set_focus(true).await;
+            },
+            onkeydown: move |evt| match evt.key() {
+                Key::ArrowLeft if has_prev => on_prev.call(()),
+                Key::ArrowRight if has_next => on_next.call(()),
+                Key::Escape => on_close.call(()),
+                _ => {}
+            },
+            ontouchstart: move |evt| {
+                let x = evt.touches().first().map(|t| t.client_coordinates().x);
+                touch_start.set(x);
+            },
+            ontouchend: move |evt| {
+                let Some(start) = touch_start.take() else {
+                    return;
+                };
+                let Some(end) = evt.touches_changed().first().map(|t| t.client_coordinates().x) else {
+                    return;
+                };
+                if end - start > SWIPE_DISTANCE && has_prev {
+                    on_prev.call(());
+                } else if start - end > SWIPE_DISTANCE && has_next {
+                    on_next.call(());
+                }
+            },
+            div { class: "lightbox-image",
+                img { key: "{id}", src: src.clone() }
+            }
+            button {
+                class: "lightbox-prev",
+                disabled: !has_prev,
+                onclick: move |_| on_prev.call(()),
+                "◀"
+            }
+            button {
+                class: "lightbox-next",
+                disabled: !has_next,
+                onclick: move |_| on_next.call(()),
+                "▶"
+            }
+            div { class: "lightbox-bar",
+                div { class: "lightbox-info",
+                    div { "{saved}" }
+                    a { href: cat.source_url.clone(), target: "_blank", "{cat.source_url}" }
+                }
+                a {
+                    class: "lightbox-download",
+                    href: src,
+                    download: "cat-{id}",
+                    target: "_blank",
+                    "⬇️"
+                }
+                button { onclick: move |_| on_delete.call(id), "🚫" }
+                button { onclick: move |_| on_close.call(()), "✖️" }
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "inline_style"))]
+#[component]
+fn MyStyle() -> Element {
+    rsx! {
+        document::Stylesheet { href: asset!("/assets/lightbox.css") }
+    }
+}
+
+#[cfg(feature = "inline_style")]
+#[component]
+fn MyStyle() -> Element {
+    const LIGHTBOX_CSS: &str = const_css_minify::minify!("../../assets/lightbox.css");
+    rsx! {
+        style { "{LIGHTBOX_CSS}" }
+    }
+}
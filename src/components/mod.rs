@@ -1,12 +1,18 @@
 mod info;
 pub use info::*;
 
+mod lightbox;
+pub use lightbox::*;
+
 mod loading;
 pub use loading::*;
 
 mod navbar;
 pub use navbar::*;
 
+mod offline_sync;
+pub use offline_sync::*;
+
 mod pagenotfound;
 pub use pagenotfound::*;
 
@@ -10,7 +10,14 @@ pub fn NavBar() -> Element {
                 h1 { "🐱 Cat's Tongue! 👅" }
             }
             {}
-            Link { to: Route::Favorites {}, id: "heart", "♥️" }
+            div {
+                Link { to: Route::Favorites {}, id: "heart", "♥️" }
+                Link { to: Route::Login {}, id: "account", "👤" }
+                // The backend server is selectable only on the app
+                if cfg!(any(feature = "desktop", feature = "mobile")) {
+                    Link { to: Route::Settings {}, id: "settings-link", "⚙️" }
+                }
+            }
         }
         Outlet::<Route> {}
     }
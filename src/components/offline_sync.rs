@@ -0,0 +1,26 @@
+use async_sleep_aki::async_sleep;
+use dioxus::prelude::*;
+
+// The interval to send the pending changes, while the server is not reachable
+const SYNC_INTERVAL_MS: i32 = 30_000;
+
+/// the component of the sync of the pending changes of favorites
+///
+/// The changes, that are queued while the server is not reachable,
+/// are sent when the server is reachable again.
+#[component]
+pub fn OfflineSync() -> Element {
+    use_effect(move || {
+        spawn(async move {
+            loop {
+                async_sleep(SYNC_INTERVAL_MS).await;
+                match crate::offline::sync().await {
+                    Ok(0) => {}
+                    Ok(count) => dioxus_logger::tracing::info!("sent {count} pending changes"),
+                    Err(e) => dioxus_logger::tracing::debug!("sync: {e}"),
+                }
+            }
+        });
+    });
+    rsx! {}
+}
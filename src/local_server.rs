@@ -0,0 +1,115 @@
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::time::Duration;
+
+// The server of the local mode, that is built with the `server` feature
+const SERVER_NAME: &str = "cattongue-server";
+
+// The local server is killed when the stdin is closed, that is when the app exits
+static LOCAL_SERVER: OnceLock<Mutex<Child>> = OnceLock::new();
+
+/// Return the path of the executable of the local server
+///
+/// It is `CATTONGUE_LOCAL_SERVER`, or `cattongue-server` beside the app.
+pub fn local_server_path() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os("CATTONGUE_LOCAL_SERVER") {
+        return Ok(PathBuf::from(path));
+    }
+    let exe = std::env::current_exe()?;
+    let Some(dir) = exe.parent() else {
+        return Err(anyhow::anyhow!("could NOT get the directory of the app"));
+    };
+    Ok(dir.join(format!("{SERVER_NAME}{}", std::env::consts::EXE_SUFFIX)))
+}
+
+/// Return the data directory of the local server
+///
+/// e.g. `~/.local/share/cattongue` on linux, with the database and `config.toml`.
+pub fn local_data_dir() -> Option<PathBuf> {
+    crate::settings::app_data_dir()
+}
+
+/// Start the local server on the loopback address, and return the url of it
+///
+/// The server listens on a free port, that it binds and keeps, and exits with the app.
+pub fn start_local_server() -> Result<String> {
+    let server_path = local_server_path()?;
+    if !server_path.exists() {
+        return Err(anyhow::anyhow!(
+            "the local server is not found: {}",
+            server_path.display()
+        ));
+    }
+    let Some(data_dir) = local_data_dir() else {
+        return Err(anyhow::anyhow!("could NOT get the data directory"));
+    };
+    std::fs::create_dir_all(&data_dir)?;
+    // The own configuration, not the one of the server of this computer
+    let config_path = data_dir.join("config.toml");
+    if !config_path.exists() {
+        std::fs::write(&config_path, "# the configuration of the local server\n")?;
+    }
+    let log = std::fs::File::create(data_dir.join("server.log"))?;
+    // The server listens on a free port, that it writes to the stdout
+    let mut child = Command::new(&server_path)
+        .arg("--config")
+        .arg(&config_path)
+        .env("IP", Ipv4Addr::LOCALHOST.to_string())
+        .env("PORT", "0")
+        .env("CATTONGUE_DB_BASE_PATH", &data_dir)
+        .env("CATTONGUE_EXIT_ON_STDIN_EOF", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(log.try_clone()?)
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to start '{}': {e}", server_path.display()))?;
+    let stdout = child.stdout.take();
+    let child = LOCAL_SERVER.get_or_init(|| Mutex::new(child));
+    let Some(stdout) = stdout else {
+        return Err(anyhow::anyhow!(
+            "could NOT read the stdout of the local server"
+        ));
+    };
+    let url = wait_for_listening(child, stdout, log)?;
+    dioxus_logger::tracing::info!("started the local server on {url}");
+    Ok(url)
+}
+
+// The app connects to the server after it listens, e.g. after the migration of the database
+fn wait_for_listening(child: &Mutex<Child>, stdout: ChildStdout, mut log: File) -> Result<String> {
+    let (tx, rx) = mpsc::channel();
+    // The rest of the stdout is the log of the server
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            match line.strip_prefix("listening on ") {
+                Some(url) => {
+                    let _ = tx.send(url.to_string());
+                }
+                None => {
+                    let _ = writeln!(log, "{line}");
+                }
+            }
+        }
+    });
+    match rx.recv_timeout(Duration::from_secs(30)) {
+        Ok(url) => Ok(url),
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            Err(anyhow::anyhow!("the local server does not listen"))
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            let status = match child.lock() {
+                Ok(mut child) => child.wait()?.to_string(),
+                Err(_) => "unknown".to_string(),
+            };
+            Err(anyhow::anyhow!("the local server exited: {status}"))
+        }
+    }
+}
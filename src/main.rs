@@ -3,10 +3,15 @@ use dioxus::prelude::*;
 use dioxus_desktop::{Config, WindowBuilder};
 
 use components::*;
-use views::{CatView, Favorites};
+use views::{CatView, Favorites, Login, Sessions, Settings, Trash};
 
 mod backends;
 mod components;
+#[cfg(feature = "desktop")]
+mod local_server;
+mod offline;
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+mod settings;
 mod views;
 
 fn main() {
@@ -24,15 +29,19 @@ fn main() {
     let level = dioxus_logger::tracing::Level::DEBUG;
     dioxus_logger::init(level).expect("failed to init logger");
 
-    // In the case of release desktop and release mobile,
-    // connect backend calls to public api
-    #[cfg(not(debug_assertions))]
+    // In the case of desktop and mobile,
+    // connect backend calls to the server of the settings, the local server, or the public api.
+    // The debug build connects to the server of `dx serve`, if no server is selected.
+    // The url can be set only once, then the change of it takes effect at the next launch.
     #[cfg(any(feature = "desktop", feature = "mobile"))]
     {
-        // Specify the URL that previously delpoyed the public webapp.
-        // This webapp was created with `dx bundle --web`.
-        let backend_url = "https://aki.omusubi.org/cattongue";
-        dioxus_fullstack::set_server_url(backend_url);
+        let settings = crate::settings::load_settings();
+        let backend_url = settings.launch_backend_url();
+        #[cfg(not(debug_assertions))]
+        let backend_url = backend_url.or_else(|| Some(settings.backend_url().to_string()));
+        if let Some(backend_url) = backend_url {
+            dioxus_fullstack::set_server_url(backend_url.leak());
+        }
     }
 
     // In the case of only release desktop, set a window title
@@ -54,26 +63,66 @@ fn main() {
     dioxus::launch(App);
 
     #[cfg(feature = "server")]
-    dioxus::serve(|| async {
-        let cookie_path = match std::env::var("DIOXUS_ASSET_ROOT") {
-            Ok(s) => format!("/{s}"),
-            Err(_e) => "/".to_string(),
-        };
-        dioxus_logger::tracing::info!("cookie_path: '{}'", &cookie_path);
-        let session_layer = {
-            use tower_sessions::{cookie::time::Duration, Expiry, SessionManagerLayer};
-            let store = crate::backends::session_store().await.unwrap();
-            SessionManagerLayer::new(store)
-                .with_name("cttg.sid")
-                .with_path(cookie_path)
-                .with_secure(false) // https
-                .with_always_save(false)
-                .with_expiry(Expiry::OnInactivity(Duration::days(30)))
-                .with_same_site(tower_sessions::cookie::SameSite::Lax)
-        };
-        //
-        Ok(dioxus::server::router(App).layer(session_layer))
-    })
+    {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if let Err(e) = crate::backends::init_config(&args) {
+            eprintln!("config: {e}");
+            std::process::exit(1);
+        }
+    }
+
+    // `PORT=0`, e.g. the local server of the desktop app
+    #[cfg(feature = "server")]
+    if crate::backends::config().server.port == Some(0) {
+        serve_on_free_port();
+    }
+
+    #[cfg(feature = "server")]
+    dioxus::serve(server_router)
+}
+
+#[cfg(feature = "server")]
+async fn server_router() -> anyhow::Result<dioxus::server::axum::Router> {
+    crate::backends::init_database().await?;
+    crate::backends::init_identity().await?;
+    crate::backends::init_provider().await?;
+    crate::backends::init_image_cache().await?;
+    //
+    let session_store = crate::backends::session_store().await?;
+    crate::backends::start_maintenance(session_store.clone())?;
+    //
+    let router = dioxus::server::router(App);
+    crate::backends::with_session_layer(router, session_store)
+}
+
+/// Serve on a free port, and write the address to the stdout as `listening on {url}`
+///
+/// The listener is kept from the bind, so the port is never taken by the other,
+/// and the parent process connects after reading the address.
+#[cfg(feature = "server")]
+fn serve_on_free_port() -> ! {
+    let ip = crate::backends::config()
+        .server
+        .ip
+        .unwrap_or(std::net::Ipv4Addr::LOCALHOST.into());
+    let r = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(anyhow::Error::from)
+        .and_then(|rt| {
+            rt.block_on(async {
+                let listener = tokio::net::TcpListener::bind((ip, 0)).await?;
+                let router = server_router().await?;
+                let addr = listener.local_addr()?;
+                println!("listening on http://{addr}");
+                dioxus::server::axum::serve(listener, router).await?;
+                Ok(())
+            })
+        });
+    if let Err(e) = r {
+        eprintln!("serve: {e}");
+    }
+    std::process::exit(1);
 }
 
 const FAVICON: Asset = asset!("/assets/favicon.ico");
@@ -89,6 +138,7 @@ fn App() -> Element {
         document::Link { rel: "icon", href: FAVICON }
         MyStyle {}
         Info {}
+        OfflineSync {}
         Router::<Route> {}
         Version {}
     }
@@ -119,6 +169,14 @@ enum Route {
     CatView,
     #[route("/favorites")]
     Favorites,
+    #[route("/login")]
+    Login,
+    #[route("/sessions")]
+    Sessions,
+    #[route("/settings")]
+    Settings,
+    #[route("/trash")]
+    Trash,
     // We can collect the segments of the URL into a Vec<String>
     #[route("/:..segments")]
     PageNotFound { segments: Vec<String> },
@@ -0,0 +1,634 @@
+use crate::backends::{CatItem, CatsPage, NewCat, Preferences, SaveStatus};
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+use dioxus::prelude::document;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// The changes are failed after this count of rejections by the server,
+// e.g. the url is rejected, then they are shown to the user
+const MAX_ATTEMPTS: u32 = 3;
+
+// The state is loaded from the storage at the first use, then it is kept in memory
+static STATE: Mutex<Option<OfflineState>> = Mutex::new(None);
+
+static IS_SYNCING: AtomicBool = AtomicBool::new(false);
+
+/// the change of favorites, that is not sent to the server yet
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum PendingChange {
+    Save(NewCat),
+    Delete(i64),
+}
+
+/// the result of `save_cat()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveResult {
+    /// the cat is sent to the server
+    Sent(SaveStatus),
+    /// the server is not reachable, the cat is sent later
+    Queued,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct QueueEntry {
+    seq: u64,
+    /// the key of the owner, that the change is sent as
+    #[serde(default)]
+    owner: Option<String>,
+    change: PendingChange,
+    attempts: u32,
+}
+
+/// the change that is rejected by the server, it is kept until the user dismisses it
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FailedChange {
+    owner: Option<String>,
+    pub change: PendingChange,
+    /// the error of the last attempt
+    pub error: String,
+}
+
+impl std::fmt::Display for FailedChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.change {
+            PendingChange::Save(cat) => write!(f, "save '{}': {}", cat.url, self.error),
+            PendingChange::Delete(id) => write!(f, "delete #{id}: {}", self.error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct CachedPage {
+    /// the cursor of `list_cats_page()`, `None` is the first page
+    cursor: Option<String>,
+    page: CatsPage,
+}
+
+/// the state of the client, that is kept over the launches
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct OfflineState {
+    next_seq: u64,
+    /// the key of the owner of this device, `Identity::owner_key`
+    owner: Option<String>,
+    queue: Vec<QueueEntry>,
+    failed: Vec<FailedChange>,
+    pages: Vec<CachedPage>,
+    /// the preferences of the last fetch, or of the change on this device
+    preferences: Option<Preferences>,
+}
+
+impl OfflineState {
+    fn push(&mut self, change: PendingChange) {
+        self.next_seq += 1;
+        self.queue.push(QueueEntry {
+            seq: self.next_seq,
+            owner: self.owner.clone(),
+            change,
+            attempts: 0,
+        });
+    }
+
+    // The changes of the other owners are kept, until they are the owner again
+    fn own_queue(&self) -> impl Iterator<Item = &QueueEntry> {
+        self.queue.iter().filter(|entry| entry.owner == self.owner)
+    }
+
+    // The pending changes are shown as if they were sent
+    fn apply_pending(&self, mut page: CatsPage, is_first: bool) -> CatsPage {
+        for entry in self.own_queue() {
+            match &entry.change {
+                PendingChange::Delete(id) => {
+                    page.items.retain(|cat| cat.id != *id);
+                    page.total = page.total.saturating_sub(1);
+                }
+                PendingChange::Save(cat) => {
+                    // The cat on the page is saved already, e.g. sent by the other device
+                    if page.items.iter().any(|c| c.url == cat.url) {
+                        continue;
+                    }
+                    page.total += 1;
+                    // The newest cats are on the first page
+                    if is_first {
+                        page.items.insert(0, pending_cat_item(entry.seq, cat));
+                    }
+                }
+            }
+        }
+        page
+    }
+}
+
+// The cat that is not saved yet has the negative id of the sequence of the queue
+fn pending_cat_item(seq: u64, cat: &NewCat) -> CatItem {
+    CatItem {
+        id: -(seq as i64),
+        url: cat.url.clone(),
+        width: cat.width,
+        height: cat.height,
+        image_hash: None,
+        source_url: cat.url.clone(),
+        create_at: String::new(),
+        tags: Vec::new(),
+        album: None,
+    }
+}
+
+/// Return the cached page of favorites at `cursor`, if it was fetched before
+pub async fn cached_favorites(cursor: Option<String>) -> Option<CatsPage> {
+    ensure_loaded().await;
+    let guard = STATE.lock().ok()?;
+    let state = guard.as_ref()?;
+    let cached = state.pages.iter().find(|cached| cached.cursor == cursor)?;
+    Some(state.apply_pending(cached.page.clone(), cursor.is_none()))
+}
+
+/// Send the pending changes, then fetch the page of favorites from the server and cache it
+pub async fn fetch_favorites(cursor: Option<String>) -> anyhow::Result<CatsPage> {
+    let _ = sync().await;
+    let page = crate::backends::list_cats_page(cursor.clone(), None).await?;
+    update(|state| {
+        let is_first = cursor.is_none();
+        state.pages.retain(|cached| cached.cursor != cursor);
+        state.pages.push(CachedPage {
+            cursor,
+            page: page.clone(),
+        });
+        state.apply_pending(page, is_first)
+    })
+    .await
+    .ok_or_else(|| anyhow::anyhow!("the offline state is not available"))
+}
+
+/// Return the cached preferences, if they were fetched before
+pub async fn cached_preferences() -> Option<Preferences> {
+    with_state(|state| state.preferences.clone())
+        .await
+        .flatten()
+}
+
+/// Fetch the preferences from the server and cache them
+pub async fn fetch_preferences() -> anyhow::Result<Preferences> {
+    let preferences = crate::backends::get_preferences().await?;
+    update(|state| state.preferences = Some(preferences.clone())).await;
+    Ok(preferences)
+}
+
+/// Save the preferences, they are kept on this device if the server is not reachable
+pub async fn save_preferences(preferences: Preferences) {
+    update(|state| state.preferences = Some(preferences.clone())).await;
+    if let Err(e) = crate::backends::set_preferences(preferences).await {
+        dioxus_logger::tracing::info!("keep the preferences on this device: {e}");
+    }
+}
+
+/// Save the cat, or queue it if the server is not reachable
+///
+/// The error is of the server that rejects the cat, it is not queued.
+pub async fn save_cat(cat: NewCat) -> anyhow::Result<SaveResult> {
+    let is_pending = with_state(|state| {
+        state
+            .own_queue()
+            .any(|entry| matches!(&entry.change, PendingChange::Save(c) if c.url == cat.url))
+    })
+    .await;
+    if is_pending == Some(true) {
+        return Ok(SaveResult::Queued);
+    }
+    match crate::backends::save_cat(cat.clone()).await {
+        Ok(r) => Ok(SaveResult::Sent(r)),
+        Err(e) if is_unreachable(&e) => {
+            dioxus_logger::tracing::info!("queue the cat to save: {e}");
+            update(|state| state.push(PendingChange::Save(cat))).await;
+            Ok(SaveResult::Queued)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Delete the cats in a transaction, or queue them if the server is not reachable
+///
+/// The error is of the server that rejects the deletion, it is not queued.
+pub async fn delete_cats(ids: Vec<i64>) -> anyhow::Result<()> {
+    // The cats that are not sent yet are only dropped from the queue
+    let (seqs, ids): (Vec<i64>, Vec<i64>) = ids.into_iter().partition(|id| *id < 0);
+    if !seqs.is_empty() {
+        update(|state| {
+            state
+                .queue
+                .retain(|entry| !seqs.contains(&-(entry.seq as i64)))
+        })
+        .await;
+    }
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let r = match crate::backends::delete_cats(ids.clone()).await {
+        Ok(count) => Some(count),
+        Err(e) if is_unreachable(&e) => {
+            dioxus_logger::tracing::info!("queue the cats to delete: {e}");
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    update(|state| {
+        for cached in state.pages.iter_mut() {
+            cached.page.items.retain(|cat| !ids.contains(&cat.id));
+        }
+        match r {
+            Some(count) => {
+                for cached in state.pages.iter_mut() {
+                    cached.page.total = cached.page.total.saturating_sub(count as usize);
+                }
+            }
+            None => {
+                for id in ids {
+                    state.push(PendingChange::Delete(id));
+                }
+            }
+        }
+    })
+    .await;
+    Ok(())
+}
+
+/// Restore the cats from the trash, or cancel the deletions that are not sent yet
+///
+/// Return the count of the restored cats, that excludes the purged ones.
+pub async fn restore_cats(ids: Vec<i64>) -> anyhow::Result<u64> {
+    let cancelled = update(|state| {
+        let mut cancelled = Vec::new();
+        let owner = state.owner.clone();
+        state.queue.retain(|entry| match entry.change {
+            PendingChange::Delete(id) if entry.owner == owner && ids.contains(&id) => {
+                cancelled.push(id);
+                false
+            }
+            _ => true,
+        });
+        cancelled
+    })
+    .await
+    .unwrap_or_default();
+    let ids: Vec<i64> = ids
+        .into_iter()
+        .filter(|id| !cancelled.contains(id))
+        .collect();
+    let count = cancelled.len() as u64;
+    if ids.is_empty() {
+        return Ok(count);
+    }
+    Ok(count + crate::backends::restore_cats(ids).await?)
+}
+
+/// Return the count of the changes, that are not sent yet
+pub async fn pending_count() -> usize {
+    with_state(|state| state.own_queue().count())
+        .await
+        .unwrap_or(0)
+}
+
+/// Return the changes that are rejected by the server, the oldest first
+pub async fn failed_changes() -> Vec<FailedChange> {
+    with_state(|state| {
+        state
+            .failed
+            .iter()
+            .filter(|failed| failed.owner == state.owner)
+            .cloned()
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Forget the changes that are rejected by the server, after they are shown to the user
+pub async fn dismiss_failed() {
+    update(|state| {
+        let owner = state.owner.clone();
+        state.failed.retain(|failed| failed.owner != owner);
+    })
+    .await;
+}
+
+/// Send the pending changes in order, and return the count of the sent ones
+///
+/// The conflicts are resolved by the server:
+/// the cat that is already saved is not saved twice, and the deleted cat is not deleted twice.
+pub async fn sync() -> anyhow::Result<usize> {
+    if pending_count().await == 0 || IS_SYNCING.swap(true, Ordering::AcqRel) {
+        return Ok(0);
+    }
+    let r = sync_queue().await;
+    IS_SYNCING.store(false, Ordering::Release);
+    r
+}
+
+async fn sync_queue() -> anyhow::Result<usize> {
+    // The failure of this means that the server is not reachable,
+    // it is without a session and changes nothing
+    crate::backends::server_info().await?;
+    let mut count = 0;
+    while let Some(entry) = with_state(|state| state.own_queue().next().cloned())
+        .await
+        .flatten()
+    {
+        let r = match entry.change.clone() {
+            PendingChange::Save(cat) => crate::backends::save_cat(cat).await.map(|_| ()),
+            PendingChange::Delete(id) => crate::backends::delete_cat(id).await,
+        };
+        let e = match r {
+            Ok(()) => {
+                update(|state| state.queue.retain(|e| e.seq != entry.seq)).await;
+                count += 1;
+                continue;
+            }
+            // The change is sent at the next time
+            Err(e) if is_unreachable(&e) => return Err(e),
+            Err(e) => e,
+        };
+        // The change is rejected by the server, then the next ones are sent after it is failed
+        let is_failed = update(|state| {
+            let pos = state.queue.iter().position(|e| e.seq == entry.seq)?;
+            state.queue[pos].attempts += 1;
+            if state.queue[pos].attempts < MAX_ATTEMPTS {
+                return Some(false);
+            }
+            let entry = state.queue.remove(pos);
+            dioxus_logger::tracing::warn!("the change is failed: {:?}: {e}", entry.change);
+            state.failed.push(FailedChange {
+                owner: entry.owner,
+                change: entry.change,
+                error: e.to_string(),
+            });
+            Some(true)
+        })
+        .await
+        .flatten();
+        if is_failed != Some(true) {
+            return Err(e);
+        }
+    }
+    Ok(count)
+}
+
+/// Return true, if the server is not reachable, e.g. offline or the server is down
+///
+/// The other errors are the responses of the server, that are not solved by sending again.
+fn is_unreachable(e: &anyhow::Error) -> bool {
+    use dioxus_fullstack::{RequestError, ServerFnError};
+    let request = match e.downcast_ref::<ServerFnError>() {
+        Some(ServerFnError::Request(request)) => request,
+        // The proxy in front of the server is reachable, but the server is not
+        Some(ServerFnError::ServerError { code, .. }) => return matches!(code, 502..=504),
+        Some(_) => return false,
+        None => match e.downcast_ref::<RequestError>() {
+            Some(request) => request,
+            None => return false,
+        },
+    };
+    matches!(
+        request,
+        RequestError::Connect(_)
+            | RequestError::Timeout(_)
+            | RequestError::Request(_)
+            | RequestError::Body(_)
+            | RequestError::Status(_, 502..=504)
+    )
+}
+
+/// Set the owner of this device, that the changes are sent as
+///
+/// The cached favorites and preferences are forgotten, if the owner is changed.
+/// The changes of the other owners are kept, and they are sent when they are the owner again.
+pub async fn set_owner(owner_key: String) {
+    update(|state| {
+        match &state.owner {
+            Some(owner) if *owner == owner_key => return,
+            Some(_) => {
+                state.pages.clear();
+                state.preferences = None;
+            }
+            // The changes of the time before the owner are of this owner
+            None => {
+                for entry in state.queue.iter_mut().filter(|e| e.owner.is_none()) {
+                    entry.owner = Some(owner_key.clone());
+                }
+            }
+        }
+        state.owner = Some(owner_key);
+    })
+    .await;
+}
+
+/// Forget the cached favorites and preferences, e.g. the owner of this device is changed
+pub async fn clear_cache() {
+    update(|state| {
+        state.pages.clear();
+        state.preferences = None;
+    })
+    .await;
+}
+
+/// Forget the cached favorites and the pending changes, at the logout
+pub async fn clear() {
+    update(|state| *state = OfflineState::default()).await;
+}
+
+async fn ensure_loaded() {
+    if STATE.lock().map(|state| state.is_some()).unwrap_or(true) {
+        return;
+    }
+    let loaded = load_state().await;
+    if let Ok(mut state) = STATE.lock() {
+        state.get_or_insert(loaded);
+    }
+}
+
+async fn with_state<R>(f: impl FnOnce(&mut OfflineState) -> R) -> Option<R> {
+    ensure_loaded().await;
+    let mut guard = STATE.lock().ok()?;
+    guard.as_mut().map(f)
+}
+
+// Change the state, and keep it in the storage
+async fn update<R>(f: impl FnOnce(&mut OfflineState) -> R) -> Option<R> {
+    ensure_loaded().await;
+    let (r, snapshot) = {
+        let mut guard = STATE.lock().ok()?;
+        let state = guard.as_mut()?;
+        (f(state), state.clone())
+    };
+    if let Err(e) = store_state(&snapshot).await {
+        dioxus_logger::tracing::warn!("Failed to keep the offline state: {e}");
+    }
+    Some(r)
+}
+
+// The state is kept in `offline.json` of the data directory of the app
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+fn state_path() -> Option<std::path::PathBuf> {
+    crate::settings::app_data_dir().map(|dir| dir.join("offline.json"))
+}
+
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+async fn load_state() -> OfflineState {
+    let Some(s) = state_path().and_then(|path| std::fs::read_to_string(path).ok()) else {
+        return OfflineState::default();
+    };
+    serde_json::from_str(&s).unwrap_or_else(|e| {
+        dioxus_logger::tracing::warn!("ignored the offline state: {e}");
+        OfflineState::default()
+    })
+}
+
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+async fn store_state(state: &OfflineState) -> anyhow::Result<()> {
+    let Some(path) = state_path() else {
+        return Err(anyhow::anyhow!("could NOT get the data directory"));
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // The state is replaced at once, not to be broken by the exit while writing
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string(state)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+// The state is kept in the IndexedDB of the browser
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+const OPEN_DB_JS: &str = concat!(
+    r#"const db = await new Promise((resolve, reject) => {"#,
+    r#" const req = indexedDB.open('cattongue', 1);"#,
+    r#" req.onupgradeneeded = () => req.result.createObjectStore('offline');"#,
+    r#" req.onsuccess = () => resolve(req.result);"#,
+    r#" req.onerror = () => reject(req.error);"#,
+    r#"});"#
+);
+
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+async fn load_state() -> OfflineState {
+    let js = format!(
+        "{OPEN_DB_JS}{}",
+        concat!(
+            r#"const req = db.transaction('offline', 'readonly').objectStore('offline').get('state');"#,
+            r#"return await new Promise((resolve, reject) => {"#,
+            r#" req.onsuccess = () => resolve(req.result ?? null);"#,
+            r#" req.onerror = () => reject(req.error);"#,
+            r#"});"#
+        )
+    );
+    match document::eval(&js).join::<Option<OfflineState>>().await {
+        Ok(state) => state.unwrap_or_default(),
+        Err(e) => {
+            dioxus_logger::tracing::warn!("ignored the offline state: {e}");
+            OfflineState::default()
+        }
+    }
+}
+
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+async fn store_state(state: &OfflineState) -> anyhow::Result<()> {
+    let js = format!(
+        "{OPEN_DB_JS}{}",
+        concat!(
+            r#"const state = await dioxus.recv();"#,
+            r#"const tx = db.transaction('offline', 'readwrite');"#,
+            r#"tx.objectStore('offline').put(state, 'state');"#,
+            r#"return await new Promise((resolve, reject) => {"#,
+            r#" tx.oncomplete = () => resolve(true);"#,
+            r#" tx.onerror = () => reject(tx.error);"#,
+            r#"});"#
+        )
+    );
+    let eval = document::eval(&js);
+    eval.send(state)?;
+    eval.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dioxus_fullstack::{RequestError, ServerFnError};
+
+    #[test]
+    fn only_unreachable_server_is_queued() {
+        let server_error = |code| ServerFnError::ServerError {
+            message: "error".to_string(),
+            code,
+            details: None,
+        };
+        let unreachable = [
+            anyhow::Error::from(RequestError::Connect("refused".to_string())),
+            anyhow::Error::from(RequestError::Timeout("timeout".to_string())),
+            anyhow::Error::from(ServerFnError::Request(RequestError::Request(
+                "offline".to_string(),
+            ))),
+            anyhow::Error::from(server_error(502)),
+            anyhow::Error::from(server_error(504)),
+        ];
+        for e in unreachable {
+            assert!(is_unreachable(&e), "{e}");
+        }
+        let rejected = [
+            anyhow::anyhow!("invalid url of the cat: 'x'"),
+            anyhow::Error::from(server_error(500)),
+            anyhow::Error::from(server_error(401)),
+            anyhow::Error::from(RequestError::Status("not found".to_string(), 404)),
+        ];
+        for e in rejected {
+            assert!(!is_unreachable(&e), "{e}");
+        }
+    }
+
+    #[test]
+    fn queue_of_other_owner_is_kept() {
+        let mut state = OfflineState {
+            owner: Some("a".to_string()),
+            ..Default::default()
+        };
+        state.push(PendingChange::Delete(1));
+        state.owner = Some("b".to_string());
+        state.push(PendingChange::Delete(2));
+        let own: Vec<_> = state.own_queue().map(|e| e.change.clone()).collect();
+        assert_eq!(own, vec![PendingChange::Delete(2)]);
+        assert_eq!(state.queue.len(), 2);
+    }
+
+    #[test]
+    fn pending_save_of_the_shown_cat_is_not_counted() {
+        let mut state = OfflineState::default();
+        let shown = pending_cat_item(
+            100,
+            &NewCat {
+                url: "https://example.com/a.png".to_string(),
+                ..Default::default()
+            },
+        );
+        let page = CatsPage {
+            items: vec![CatItem { id: 1, ..shown }],
+            total: 1,
+            ..Default::default()
+        };
+        for url in ["https://example.com/a.png", "https://example.com/b.png"] {
+            state.push(PendingChange::Save(NewCat {
+                url: url.to_string(),
+                ..Default::default()
+            }));
+        }
+        let first = state.apply_pending(page.clone(), true);
+        let urls: Vec<_> = first.items.iter().map(|cat| cat.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            ["https://example.com/b.png", "https://example.com/a.png"]
+        );
+        assert_eq!(first.total, 2);
+        // The pending cats are shown only on the first page
+        let other = state.apply_pending(page, false);
+        assert_eq!(other.items.len(), 1);
+        assert_eq!(other.total, 2);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::backends::ServerInfo;
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// the url of the backend server, if it is not set in the settings
+///
+/// This is the server that deployed the public webapp with `dx bundle --web`.
+pub const DEFAULT_BACKEND_URL: &str = "https://aki.omusubi.org/cattongue";
+
+// The package name of the android app, that is the `bundle.identifier` of `Dioxus.toml`
+#[cfg(target_os = "android")]
+const ANDROID_PACKAGE: &str = "org.omusubi.aki.nt.cattongue";
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// the settings of the app, that are kept in the platform config directory
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// the url of the backend server, e.g. `https://example.com/cattongue`
+    pub backend_url: Option<String>,
+    /// true, if the desktop app uses the local server on this computer instead
+    pub local_server: bool,
+}
+
+impl AppSettings {
+    /// Return the url of the backend server, or the default
+    pub fn backend_url(&self) -> &str {
+        self.backend_url.as_deref().unwrap_or(DEFAULT_BACKEND_URL)
+    }
+
+    /// Return the url of the backend server to connect at the launch,
+    /// the local server is started if it is selected
+    ///
+    /// Return `None`, if no server is selected.
+    pub fn launch_backend_url(&self) -> Option<String> {
+        #[cfg(feature = "desktop")]
+        if self.local_server {
+            match crate::local_server::start_local_server() {
+                Ok(url) => return Some(url),
+                Err(e) => dioxus_logger::tracing::error!(
+                    "Failed to start the local server, connect to {}: {e}",
+                    self.backend_url()
+                ),
+            }
+        }
+        self.backend_url.clone()
+    }
+}
+
+/// Return the path of the settings file
+///
+/// e.g. `~/.config/cattongue/settings.toml` on linux,
+/// the files directory of the app on android.
+pub fn settings_path() -> Option<PathBuf> {
+    #[cfg(target_os = "android")]
+    let dir = Some(android_files_dir());
+    #[cfg(not(target_os = "android"))]
+    let dir = dirs::config_dir().map(|dir| dir.join("cattongue"));
+    dir.map(|dir| dir.join(SETTINGS_FILE))
+}
+
+/// Return the data directory of the app
+///
+/// e.g. `~/.local/share/cattongue` on linux,
+/// the files directory of the app on android.
+#[cfg(not(target_os = "android"))]
+pub fn app_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("cattongue"))
+}
+
+#[cfg(target_os = "android")]
+pub fn app_data_dir() -> Option<PathBuf> {
+    Some(android_files_dir())
+}
+
+#[cfg(target_os = "android")]
+fn android_files_dir() -> PathBuf {
+    PathBuf::from(format!("/data/data/{ANDROID_PACKAGE}/files"))
+}
+
+/// Load the settings, or the default if the file does not exist or is broken
+pub fn load_settings() -> AppSettings {
+    let Some(path) = settings_path() else {
+        return AppSettings::default();
+    };
+    let Ok(s) = std::fs::read_to_string(&path) else {
+        return AppSettings::default();
+    };
+    match toml::from_str(&s) {
+        Ok(settings) => settings,
+        Err(e) => {
+            dioxus_logger::tracing::warn!("ignored the settings {}: {e}", path.display());
+            AppSettings::default()
+        }
+    }
+}
+
+/// Save the settings into the settings file
+pub fn save_settings(settings: &AppSettings) -> Result<()> {
+    let Some(path) = settings_path() else {
+        return Err(anyhow::anyhow!("could NOT get the config directory"));
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, toml::to_string(settings)?)?;
+    Ok(())
+}
+
+/// Return the url of the backend server without the trailing `/`,
+/// or an error if it is not a http url
+pub fn normalize_backend_url(url: &str) -> Result<String> {
+    let url = url.trim().trim_end_matches('/');
+    let host = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"));
+    match host {
+        Some(host) if !host.is_empty() && !host.contains(char::is_whitespace) => {
+            Ok(url.to_string())
+        }
+        _ => Err(anyhow::anyhow!(
+            "the url must start with `http://` or `https://`"
+        )),
+    }
+}
+
+/// Check the connection to the backend server of `url`, and return the information of it
+pub async fn check_backend(url: &str) -> Result<ServerInfo> {
+    let url = normalize_backend_url(url)?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()?;
+    let info: ServerInfo = client
+        .get(format!("{url}/api/v1/server_info"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if info.name != env!("CARGO_PKG_NAME") {
+        return Err(anyhow::anyhow!(
+            "the server is not cattongue: {}",
+            info.name
+        ));
+    }
+    Ok(info)
+}
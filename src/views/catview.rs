@@ -1,16 +1,9 @@
+use crate::backends::{image_src, NewCat, SaveStatus};
+use crate::offline::SaveResult;
 use crate::OverlaySpinner;
 use async_sleep_aki::{async_sleep, delayed_call};
 use dioxus::prelude::*;
 
-#[allow(dead_code)]
-#[derive(serde::Deserialize)]
-struct CatApi {
-    id: String,
-    url: String,
-    width: i64,
-    height: i64,
-}
-
 async fn check_complete(mut is_loading: Signal<bool>) {
     async_sleep(400).await;
     let js: &str = concat!(
@@ -43,34 +36,59 @@ async fn check_complete(mut is_loading: Signal<bool>) {
     }
 }
 
+// The notice on the save button for a while after saving
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SaveNotice {
+    AlreadySaved,
+    Queued,
+    Failed,
+}
+
+impl SaveNotice {
+    fn class(self) -> &'static str {
+        match self {
+            SaveNotice::AlreadySaved => "already-saved",
+            SaveNotice::Queued => "queued",
+            SaveNotice::Failed => "failed",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SaveNotice::AlreadySaved => "already saved",
+            SaveNotice::Queued => "saved later",
+            SaveNotice::Failed => "not saved",
+        }
+    }
+}
+
 /// the component of the `Cat page`
 #[component]
 pub fn CatView() -> Element {
     let mut is_loading = use_signal(|| false);
     let mut loading_count = use_signal(|| 0i64);
+    let mut notice = use_signal(|| None::<SaveNotice>);
     let mut img_src = use_resource(move || async move {
         is_loading.set(true);
         loading_count += 1;
-        //let url = "https://aws.random.cat/meow";
-        let url = "https://api.thecatapi.com/v1/images/search";
-        let resp = reqwest::get(url).await;
-        let r = if let Err(_e) = resp {
-            dioxus_logger::tracing::info!("error: {_e}");
-            is_loading.set(false);
-            "".to_string()
-        } else {
-            let body = resp.unwrap();
-            let r1 = body.json::<Vec<CatApi>>().await.unwrap()[0].url.clone();
-            spawn(async move {
-                async_sleep(20).await;
-                spawn(delayed_call(2000, async move {
-                    if *is_loading.read() {
-                        is_loading.set(false);
-                    }
-                }));
-                spawn(check_complete(is_loading));
-            });
-            r1
+        let r = match crate::backends::next_cat().await {
+            Err(_e) => {
+                dioxus_logger::tracing::info!("error: {_e}");
+                is_loading.set(false);
+                None
+            }
+            Ok(r1) => {
+                spawn(async move {
+                    async_sleep(20).await;
+                    spawn(delayed_call(2000, async move {
+                        if *is_loading.read() {
+                            is_loading.set(false);
+                        }
+                    }));
+                    spawn(check_complete(is_loading));
+                });
+                Some(r1)
+            }
         };
         loading_count -= 1;
         if *loading_count.read() > 0 {
@@ -79,20 +97,45 @@ pub fn CatView() -> Element {
         r
     });
 
+    // The cat is `None` while it is loading or it failed to load
+    let cat: Option<NewCat> = img_src.cloned().flatten();
+    let url = cat.as_ref().map(|cat| cat.url.as_str()).unwrap_or_default();
+
     rsx! {
         div { id: "catview",
-            img { id: "catimg", src: img_src.cloned().unwrap_or_default() }
+            img { id: "catimg", src: image_src(url) }
         }
         div { id: "buttons",
             button { onclick: move |_| img_src.restart(), id: "skip", "skip" }
             button {
+                disabled: cat.is_none(),
                 onclick: move |_| async move {
-                    let current = img_src.cloned().unwrap();
+                    let Some(current) = img_src.cloned().flatten() else {
+                        return;
+                    };
                     img_src.restart();
-                    _ = crate::backends::save_cat(current).await;
+                    // The cat is queued, if the server is not reachable
+                    let r = match crate::offline::save_cat(current).await {
+                        Ok(SaveResult::Sent(SaveStatus::Saved)) => None,
+                        Ok(SaveResult::Sent(SaveStatus::AlreadySaved)) => {
+                            Some(SaveNotice::AlreadySaved)
+                        }
+                        Ok(SaveResult::Queued) => Some(SaveNotice::Queued),
+                        Err(e) => {
+                            dioxus_logger::tracing::info!("save_cat: {e}");
+                            Some(SaveNotice::Failed)
+                        }
+                    };
+                    if r.is_some() {
+                        notice.set(r);
+                        spawn(delayed_call(1500, async move {
+                            notice.set(None);
+                        }));
+                    }
                 },
                 id: "save",
-                "save!"
+                class: notice().map(SaveNotice::class),
+                {notice().map(SaveNotice::label).unwrap_or("save!")}
             }
         }
         if *is_loading.read() {
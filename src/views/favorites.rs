@@ -1,61 +1,480 @@
-use crate::OverlaySpinner;
+use crate::backends::{
+    download_selected_cats, image_src, thumb_srcset, thumb_url, CatItem, CatsPage, FavoritesLayout,
+    Preferences, EXPORT_FORMATS, MAX_BULK_IDS,
+};
+use crate::offline::FailedChange;
+use crate::{Lightbox, OverlaySpinner, Route};
+use async_sleep_aki::async_sleep;
 use dioxus::prelude::*;
-use dioxus_fullstack::Loader;
+use std::collections::HashSet;
+
+// The time that the deletion can be undone, in milliseconds
+const UNDO_TOAST_MS: i32 = 6_000;
 
 /// the component of the `Favorites page`
+///
+/// The cached favorites are shown first, then they are refreshed from the server.
+/// The next page is loaded by the buttons, or by the scroll near the bottom.
+/// The lightbox steps through the pages, too.
+/// In the selection mode, the selected cats are deleted, tagged, moved to an album
+/// or exported at once.
 #[component]
 pub fn Favorites() -> Element {
     let mut is_loading = use_signal(|| false);
-    let offset = use_signal(|| 0usize);
-    let count_of_cats =
-        use_loader(move || async move { crate::backends::count_of_cats("x".to_string()).await })?;
-    let favorites = use_loader(move || async move {
-        is_loading.set(true);
-        let r = crate::backends::list_cats(*offset.read()).await;
-        is_loading.set(false);
-        r
-    })?;
-    /*
-    // Create a pending resource that resolves to the list of cats from the backend
-    // Wait for the favorites list to resolve with `.suspend()`
-    let favorites = use_resource(move || async move {
-        is_loading.set(true);
-        let r = crate::backends::list_cats().await;
-        is_loading.set(false);
-        r
+    let mut is_loading_more = use_signal(|| false);
+    let mut cursor = use_signal(|| None::<String>);
+    let mut page_no = use_signal(|| 1usize);
+    let mut page = use_signal(CatsPage::default);
+    // The pages after the first page, in the infinite scroll
+    let mut more = use_signal(Vec::<CatItem>::new);
+    let mut more_cursor = use_signal(|| None::<String>);
+    let mut is_near_bottom = use_signal(|| false);
+    let mut is_more_failed = use_signal(|| false);
+    // The index of the cat in the lightbox, and the cat to view after the page is loaded
+    let mut viewing = use_signal(|| None::<usize>);
+    let mut view_after_load = use_signal(|| None::<ViewAt>);
+    // The last deleted cats, that can be restored from the trash
+    let mut undo = use_signal(Vec::<CatItem>::new);
+    let mut undo_seq = use_signal(|| 0u32);
+    // The selected ids, and the anchor of the range selection
+    let mut is_selecting = use_signal(|| false);
+    let mut selected = use_signal(HashSet::<i64>::new);
+    let mut anchor = use_signal(|| None::<i64>);
+    let mut preferences = use_signal(Preferences::default);
+    let mut pending_count = use_signal(|| 0usize);
+    // The changes that are rejected by the server, and the error of the last deletion
+    let mut failed = use_signal(Vec::<FailedChange>::new);
+    let mut message = use_signal(String::new);
+    // The path of the export of the selected cats, that the app saved
+    let mut saved = use_signal(String::new);
+    let mut reload = use_signal(|| 0u32);
+
+    use_effect(move || {
+        spawn(async move {
+            if let Some(r) = crate::offline::cached_preferences().await {
+                preferences.set(r);
+            }
+            match crate::offline::fetch_preferences().await {
+                Ok(r) => preferences.set(r),
+                Err(e) => dioxus_logger::tracing::info!("offline: {e}"),
+            }
+        });
+    });
+
+    use_effect(move || {
+        let curr = cursor.read().clone();
+        let _ = reload.read();
+        spawn(async move {
+            let mut set_page = move |r: CatsPage| {
+                // The first page is returned, if the page is gone by the deletion
+                if r.prev_cursor.is_none() {
+                    page_no.set(1);
+                }
+                more.set(Vec::new());
+                more_cursor.set(r.next_cursor.clone());
+                match *view_after_load.peek() {
+                    Some(ViewAt::First) => viewing.set(Some(0)),
+                    Some(ViewAt::Last) => viewing.set(Some(r.items.len().saturating_sub(1))),
+                    None => {}
+                }
+                page.set(r);
+            };
+            match crate::offline::cached_favorites(curr.clone()).await {
+                Some(r) => set_page(r),
+                None => is_loading.set(true),
+            }
+            let r = crate::offline::fetch_favorites(curr.clone()).await;
+            // The page may be changed while fetching
+            if *cursor.peek() == curr {
+                match r {
+                    Ok(r) => set_page(r),
+                    Err(e) => dioxus_logger::tracing::info!("offline: {e}"),
+                }
+            }
+            view_after_load.set(None);
+            pending_count.set(crate::offline::pending_count().await);
+            failed.set(crate::offline::failed_changes().await);
+            is_loading.set(false);
+        });
     });
-    let fav_sus = favorites.suspend()?;
-    */
 
+    // The next page is loaded while the bottom is near or the last cat is viewed,
+    // without the overlay spinner
+    use_effect(move || {
+        let is_infinite = preferences.read().infinite_scroll;
+        let Some(next) = more_cursor.read().clone() else {
+            return;
+        };
+        let count = page.read().items.len() + more.read().len();
+        let is_at_end = viewing.read().is_some_and(|i| i + 1 >= count);
+        if !is_infinite
+            || !(*is_near_bottom.read() || is_at_end)
+            || *is_more_failed.read()
+            || *is_loading_more.read()
+        {
+            return;
+        }
+        is_loading_more.set(true);
+        spawn(async move {
+            match crate::offline::fetch_favorites(Some(next)).await {
+                Ok(r) => {
+                    more.with_mut(|items| {
+                        for cat in r.items {
+                            if !items.iter().any(|c| c.id == cat.id) {
+                                items.push(cat);
+                            }
+                        }
+                    });
+                    more_cursor.set(r.next_cursor);
+                }
+                Err(e) => {
+                    dioxus_logger::tracing::info!("offline: {e}");
+                    // The next try is at the next scroll or step
+                    is_more_failed.set(true);
+                }
+            }
+            is_loading_more.set(false);
+        });
+    });
+
+    let delete_cats = move |ids: Vec<i64>| {
+        // The cats that are not sent yet are not in the trash
+        let deleted: Vec<CatItem> = page
+            .peek()
+            .items
+            .iter()
+            .chain(more.peek().iter())
+            .filter(|cat| cat.id > 0 && ids.contains(&cat.id))
+            .cloned()
+            .collect();
+        spawn(async move {
+            message.set(String::new());
+            if let Err(e) = crate::offline::delete_cats(ids.clone()).await {
+                message.set(format!("error: {e}"));
+                return;
+            }
+            if !deleted.is_empty() {
+                undo.set(deleted);
+                undo_seq += 1;
+            }
+            page.with_mut(|r| {
+                r.items.retain(|cat| !ids.contains(&cat.id));
+                r.total = r.total.saturating_sub(ids.len());
+            });
+            more.with_mut(|items| items.retain(|cat| !ids.contains(&cat.id)));
+            selected.with_mut(|s| s.retain(|id| !ids.contains(id)));
+            if page.peek().items.is_empty() && more.peek().is_empty() {
+                viewing.set(None);
+            }
+            // The page is filled again, but the scrolled cats are kept
+            if !preferences.peek().infinite_scroll {
+                reload += 1;
+            }
+            pending_count.set(crate::offline::pending_count().await);
+        });
+    };
+
+    let on_delete = move |id: i64| delete_cats(vec![id]);
+
+    let edit_cats = move |(ids, edit): (Vec<i64>, BulkEdit)| {
+        // The cats that are not sent yet are not on the server
+        let ids: Vec<i64> = ids.into_iter().filter(|id| *id > 0).collect();
+        spawn(async move {
+            message.set(String::new());
+            let r = match &edit {
+                BulkEdit::Tag(tag) => crate::backends::tag_cats(ids.clone(), tag.clone()).await,
+                BulkEdit::Untag(tag) => crate::backends::untag_cats(ids.clone(), tag.clone()).await,
+                BulkEdit::Move(album) => {
+                    crate::backends::move_cats(ids.clone(), album.clone()).await
+                }
+            };
+            if let Err(e) = r {
+                message.set(format!("error: {e}"));
+                return;
+            }
+            // The change is shown without reloading, the scrolled cats are kept
+            let apply = |items: &mut Vec<CatItem>| {
+                for cat in items.iter_mut().filter(|cat| ids.contains(&cat.id)) {
+                    edit.apply(cat);
+                }
+            };
+            page.with_mut(|r| apply(&mut r.items));
+            more.with_mut(apply);
+        });
+    };
+
+    let export_cats = move |format: String| {
+        let ids: Vec<i64> = selected
+            .peek()
+            .iter()
+            .copied()
+            .filter(|id| *id > 0)
+            .collect();
+        spawn(async move {
+            message.set(String::new());
+            saved.set(String::new());
+            match download_selected_cats(&format, &ids).await {
+                Ok(Some(path)) => saved.set(format!("saved: {path}")),
+                Ok(None) => {}
+                Err(e) => message.set(format!("error: {e}")),
+            }
+        });
+    };
+
+    let on_undo = move |cats: Vec<CatItem>| {
+        undo.set(Vec::new());
+        let ids = cats.iter().map(|cat| cat.id).collect();
+        spawn(async move {
+            match crate::offline::restore_cats(ids).await {
+                Ok(count) if count as usize == cats.len() && preferences.peek().infinite_scroll => {
+                    for cat in cats {
+                        // The cat is put back in place, unless it is in the pages not loaded yet
+                        let is_loaded = more_cursor.peek().is_none()
+                            || page
+                                .peek()
+                                .items
+                                .iter()
+                                .chain(more.peek().iter())
+                                .any(|c| c.id < cat.id);
+                        if !is_loaded {
+                            continue;
+                        }
+                        if more.peek().first().is_some_and(|c| c.id > cat.id) {
+                            more.with_mut(|items| insert_cat(items, cat));
+                        } else {
+                            page.with_mut(|r| insert_cat(&mut r.items, cat));
+                        }
+                    }
+                    page.with_mut(|r| r.total += count as usize);
+                }
+                Ok(0) => dioxus_logger::tracing::info!("the cats are not in the trash"),
+                Ok(_) => reload += 1,
+                Err(e) => dioxus_logger::tracing::info!("offline: {e}"),
+            }
+            pending_count.set(crate::offline::pending_count().await);
+        });
+    };
+
+    let mut select_cat = move |id: i64, is_range: bool| {
+        let ids: Vec<i64> = page
+            .peek()
+            .items
+            .iter()
+            .chain(more.peek().iter())
+            .map(|cat| cat.id)
+            .collect();
+        let at = ids.iter().position(|x| *x == id);
+        let anchor_at = anchor.peek().and_then(|a| ids.iter().position(|x| *x == a));
+        match (is_range, anchor_at, at) {
+            (true, Some(a), Some(b)) => {
+                let range = &ids[a.min(b)..=a.max(b)];
+                selected.with_mut(|s| s.extend(range.iter().copied()));
+            }
+            _ => selected.with_mut(|s| {
+                if !s.remove(&id) {
+                    s.insert(id);
+                }
+            }),
+        }
+        anchor.set(Some(id));
+    };
+
+    let on_preferences = move |r: Preferences| {
+        // The infinite scroll starts from the first page
+        if r.infinite_scroll != preferences.peek().infinite_scroll {
+            page_no.set(1);
+            cursor.set(None);
+            reload += 1;
+        }
+        preferences.set(r.clone());
+        spawn(crate::offline::save_preferences(r));
+    };
+
+    let items: Vec<CatItem> = page
+        .read()
+        .items
+        .iter()
+        .chain(more.read().iter())
+        .cloned()
+        .collect();
+    let count = items.len();
+    let total = page.read().total;
+    let is_infinite = preferences.read().infinite_scroll;
+    let layout = preferences.read().favorites_layout.as_str();
+    // The index is kept in the items, if the cats are deleted
+    let index = viewing().map(|i| i.min(count.saturating_sub(1)));
+    let viewed = index.and_then(|i| items.get(i).cloned());
+    let (has_prev, has_next) = match index {
+        Some(i) if is_infinite => (i > 0, i + 1 < count || more_cursor.read().is_some()),
+        Some(i) => (
+            i > 0 || page.read().prev_cursor.is_some(),
+            i + 1 < count || page.read().next_cursor.is_some(),
+        ),
+        None => (false, false),
+    };
+    let on_prev = move |_| {
+        let Some(i) = index else {
+            return;
+        };
+        if i > 0 {
+            viewing.set(Some(i - 1));
+        } else if let Some(prev) = page.peek().prev_cursor.clone() {
+            is_loading.set(true);
+            let n = *page_no.peek();
+            page_no.set(n.saturating_sub(1).max(1));
+            view_after_load.set(Some(ViewAt::Last));
+            cursor.set(Some(prev));
+        }
+    };
+    let on_next = move |_| {
+        let Some(i) = index else {
+            return;
+        };
+        if i + 1 < count {
+            viewing.set(Some(i + 1));
+        } else if preferences.peek().infinite_scroll {
+            // The next page is appended by the loader while the last cat is viewed
+            is_more_failed.set(false);
+        } else if let Some(next) = page.peek().next_cursor.clone() {
+            is_loading.set(true);
+            page_no += 1;
+            view_after_load.set(Some(ViewAt::First));
+            cursor.set(Some(next));
+        }
+    };
     rsx! {
         div { id: "favorites",
             div { id: "favorites-navi",
-                div {
-                    HandLeft {
-                        offset,
-                        count_of_cats,
-                        favorites,
-                        is_loading,
+                if is_infinite {
+                    div { " {total} cats " }
+                } else {
+                    div {
+                        HandLeft {
+                            page,
+                            cursor,
+                            page_no,
+                            is_loading,
+                        }
+                        " page {page_no}, {total} cats "
+                        HandRight {
+                            page,
+                            cursor,
+                            page_no,
+                            is_loading,
+                        }
                     }
-                    " {offset+1} / {count_of_cats} "
-                    HandRight {
-                        offset,
-                        count_of_cats,
-                        favorites,
-                        is_loading,
+                }
+                FavoritesLayoutMenu { preferences: preferences.cloned(), on_change: on_preferences }
+                FavoritesExport {}
+                FavoritesImport { reload, is_loading }
+                button {
+                    id: "select-mode",
+                    class: if is_selecting() { "active" },
+                    onclick: move |_| {
+                        is_selecting.toggle();
+                        selected.set(HashSet::new());
+                        anchor.set(None);
+                    },
+                    "☑️"
+                }
+                Link { to: Route::Trash {}, id: "trash-link", "🗑️" }
+            }
+            if is_selecting() {
+                SelectionBar {
+                    ids: selected.read().iter().copied().collect::<Vec<_>>(),
+                    on_select_all: move |_| {
+                        let ids: Vec<i64> = page
+                            .peek()
+                            .items
+                            .iter()
+                            .chain(more.peek().iter())
+                            .map(|cat| cat.id)
+                            .collect();
+                        selected.with_mut(|s| s.extend(ids));
+                    },
+                    on_clear: move |_| {
+                        selected.set(HashSet::new());
+                        anchor.set(None);
+                    },
+                    on_delete: move |ids: Vec<i64>| delete_cats(ids),
+                    on_edit: edit_cats,
+                    on_export: export_cats,
+                }
+            }
+            if *pending_count.read() > 0 {
+                div { class: "pending-message",
+                    "{pending_count} changes are waiting for the server"
+                }
+            }
+            if !failed.read().is_empty() {
+                div { class: "failed-message",
+                    "these changes are rejected by the server:"
+                    for change in failed.read().iter() {
+                        div { "{change}" }
+                    }
+                    button {
+                        onclick: move |_| async move {
+                            crate::offline::dismiss_failed().await;
+                            failed.set(Vec::new());
+                        },
+                        "dismiss"
                     }
                 }
             }
-            div { id: "favorites-container",
-                for (id , url) in favorites.cloned() {
-                    FavoriteCat {
-                        id,
-                        url,
-                        count_of_cats,
-                        favorites,
-                        is_loading,
+            if !message.read().is_empty() {
+                div { class: "failed-message", "{message}" }
+            }
+            if !saved.read().is_empty() {
+                div { class: "pending-message", "{saved}" }
+            }
+            div { id: "favorites-container", class: "layout-{layout}",
+                div { class: "favorites-items",
+                    for (i , cat) in items.into_iter().enumerate() {
+                        FavoriteCat {
+                            key: "{cat.id}",
+                            is_selecting: is_selecting(),
+                            is_selected: selected.read().contains(&cat.id),
+                            on_select: move |is_range: bool| select_cat(cat.id, is_range),
+                            cat,
+                            on_open: move |_| viewing.set(Some(i)),
+                            on_delete,
+                        }
                     }
                 }
+                if is_infinite {
+                    div {
+                        class: "scroll-sentinel",
+                        onvisible: move |evt| {
+                            let is_visible = evt.is_intersecting().unwrap_or(false);
+                            if is_visible {
+                                is_more_failed.set(false);
+                            }
+                            is_near_bottom.set(is_visible);
+                        },
+                    }
+                    if *is_loading_more.read() {
+                        div { class: "loading-more", "loading..." }
+                    }
+                }
+            }
+        }
+        if let Some(cat) = viewed {
+            Lightbox {
+                cat,
+                has_prev,
+                has_next,
+                on_prev,
+                on_next,
+                on_close: move |_| viewing.set(None),
+                on_delete,
+            }
+        }
+        if !undo.read().is_empty() {
+            UndoToast {
+                key: "{undo_seq}",
+                cats: undo.cloned(),
+                on_undo,
+                on_close: move |_| undo.set(Vec::new()),
             }
         }
         if *is_loading.read() {
@@ -64,23 +483,233 @@ pub fn Favorites() -> Element {
     }
 }
 
+// Insert the cat in the order of the newest first
+fn insert_cat(items: &mut Vec<CatItem>, cat: CatItem) {
+    let i = items
+        .iter()
+        .position(|c| c.id < cat.id)
+        .unwrap_or(items.len());
+    items.insert(i, cat);
+}
+
+// the cat to view in the lightbox, after the next or previous page is loaded
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ViewAt {
+    First,
+    Last,
+}
+
+/// the component of the toast to undo the deletion, that is closed after a while
+#[component]
+pub fn UndoToast(
+    cats: Vec<CatItem>,
+    on_undo: EventHandler<Vec<CatItem>>,
+    on_close: EventHandler<()>,
+) -> Element {
+    use_future(move || async move {
+        async_sleep(UNDO_TOAST_MS).await;
+        on_close.call(());
+    });
+    let text = match cats.len() {
+        1 => "the cat is moved to the trash".to_string(),
+        n => format!("{n} cats are moved to the trash"),
+    };
+    rsx! {
+        div { class: "undo-toast",
+            "{text}"
+            button { onclick: move |_| on_undo.call(cats.clone()), "undo" }
+        }
+    }
+}
+
+/// the bulk edit of the selected cats
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkEdit {
+    Tag(String),
+    Untag(String),
+    /// the empty name moves the cats out of the album
+    Move(String),
+}
+
+impl BulkEdit {
+    // The same change as the server does
+    fn apply(&self, cat: &mut CatItem) {
+        match self {
+            BulkEdit::Tag(tag) => {
+                let tag = tag.trim();
+                if let Err(at) = cat.tags.binary_search_by(|t| t.as_str().cmp(tag)) {
+                    cat.tags.insert(at, tag.to_string());
+                }
+            }
+            BulkEdit::Untag(tag) => cat.tags.retain(|t| t != tag.trim()),
+            BulkEdit::Move(album) => {
+                cat.album = Some(album.trim().to_string()).filter(|album| !album.is_empty());
+            }
+        }
+    }
+}
+
+/// the component of the bulk actions of the selected cats
+#[component]
+pub fn SelectionBar(
+    ids: Vec<i64>,
+    on_select_all: EventHandler<()>,
+    on_clear: EventHandler<()>,
+    on_delete: EventHandler<Vec<i64>>,
+    on_edit: EventHandler<(Vec<i64>, BulkEdit)>,
+    on_export: EventHandler<String>,
+) -> Element {
+    let mut tag = use_signal(String::new);
+    let mut album = use_signal(String::new);
+    // The names of the albums, that are suggested for the move
+    let mut albums = use_signal(Vec::<String>::new);
+    use_effect(move || {
+        spawn(async move {
+            match crate::backends::list_albums().await {
+                Ok(r) => albums.set(r),
+                Err(e) => dioxus_logger::tracing::info!("list_albums: {e}"),
+            }
+        });
+    });
+    let count = ids.len();
+    let is_enabled = (1..=MAX_BULK_IDS).contains(&count);
+    let is_tag_enabled = is_enabled && !tag.read().trim().is_empty();
+    let to_delete = ids.clone();
+    let to_tag = ids.clone();
+    let to_untag = ids.clone();
+    let to_move = ids;
+    rsx! {
+        div { id: "selection-bar",
+            span { "{count} selected" }
+            button { onclick: move |_| on_select_all.call(()), "select all on page" }
+            button { onclick: move |_| on_clear.call(()), "clear" }
+            button {
+                disabled: !is_enabled,
+                onclick: move |_| on_delete.call(to_delete.clone()),
+                "🚫 delete"
+            }
+            input {
+                r#type: "text",
+                placeholder: "tag",
+                value: "{tag}",
+                oninput: move |evt| tag.set(evt.value()),
+            }
+            button {
+                disabled: !is_tag_enabled,
+                onclick: move |_| on_edit.call((to_tag.clone(), BulkEdit::Tag(tag.cloned()))),
+                "🏷️ tag"
+            }
+            button {
+                disabled: !is_tag_enabled,
+                onclick: move |_| on_edit.call((to_untag.clone(), BulkEdit::Untag(tag.cloned()))),
+                "untag"
+            }
+            input {
+                r#type: "text",
+                list: "album-names",
+                placeholder: "album",
+                value: "{album}",
+                oninput: move |evt| album.set(evt.value()),
+            }
+            datalist { id: "album-names",
+                for name in albums.read().iter() {
+                    option { key: "{name}", value: "{name}" }
+                }
+            }
+            button {
+                disabled: !is_enabled,
+                onclick: move |_| {
+                    let name = album.read().trim().to_string();
+                    if !name.is_empty() && !albums.read().contains(&name) {
+                        albums.with_mut(|names| names.push(name));
+                    }
+                    on_edit.call((to_move.clone(), BulkEdit::Move(album.cloned())));
+                },
+                if album.read().trim().is_empty() {
+                    "📁 out of album"
+                } else {
+                    "📁 move"
+                }
+            }
+            if is_enabled {
+                for (format , label) in EXPORT_FORMATS.iter() {
+                    button {
+                        key: "{format}",
+                        onclick: move |_| on_export.call(format.to_string()),
+                        "⬇️ {label}"
+                    }
+                }
+            }
+            if count > MAX_BULK_IDS {
+                span { class: "pending-message", "up to {MAX_BULK_IDS} cats at once" }
+            }
+        }
+    }
+}
+
+/// the component of the menu of the layout and the infinite scroll
+#[component]
+pub fn FavoritesLayoutMenu(
+    preferences: Preferences,
+    on_change: EventHandler<Preferences>,
+) -> Element {
+    let current = preferences.favorites_layout;
+    let is_infinite = preferences.infinite_scroll;
+    let prefs = preferences.clone();
+    rsx! {
+        div { id: "favorites-layout",
+            select {
+                onchange: move |evt| {
+                    if let Some(layout) = FavoritesLayout::from_name(&evt.value()) {
+                        on_change.call(Preferences {
+                            favorites_layout: layout,
+                            ..prefs.clone()
+                        });
+                    }
+                },
+                for layout in FavoritesLayout::ALL {
+                    option {
+                        key: "{layout.as_str()}",
+                        value: layout.as_str(),
+                        selected: layout == current,
+                        "{layout.as_str()}"
+                    }
+                }
+            }
+            label {
+                input {
+                    r#type: "checkbox",
+                    checked: is_infinite,
+                    onchange: move |evt| {
+                        on_change.call(Preferences {
+                            infinite_scroll: evt.checked(),
+                            ..preferences.clone()
+                        });
+                    },
+                }
+                "scroll"
+            }
+        }
+    }
+}
+
+/// the component of the button to the page of the newer cats
 #[component]
 pub fn HandLeft(
-    offset: Signal<usize>,
-    count_of_cats: Loader<usize>,
-    favorites: Loader<Vec<(i64, String)>>,
+    page: Signal<CatsPage>,
+    cursor: Signal<Option<String>>,
+    page_no: Signal<usize>,
     is_loading: Signal<bool>,
 ) -> Element {
+    let prev_cursor = page.read().prev_cursor.clone();
     rsx! {
-        if *offset.read() >= 20 {
+        if let Some(prev_cursor) = prev_cursor {
             button {
-                onclick: move |_| async move {
+                onclick: move |_| {
                     is_loading.set(true);
-                    let curr = *offset.read();
-                    if curr >= 20 {
-                        offset.set(curr - 20);
-                    }
-                    favorites.restart();
+                    let n = *page_no.peek();
+                    page_no.set(n.saturating_sub(1).max(1));
+                    cursor.set(Some(prev_cursor.clone()));
                 },
                 id: "handleft",
                 "👈"
@@ -91,21 +720,22 @@ pub fn HandLeft(
     }
 }
 
+/// the component of the button to the page of the older cats
 #[component]
 pub fn HandRight(
-    offset: Signal<usize>,
-    count_of_cats: Loader<usize>,
-    favorites: Loader<Vec<(i64, String)>>,
+    page: Signal<CatsPage>,
+    cursor: Signal<Option<String>>,
+    page_no: Signal<usize>,
     is_loading: Signal<bool>,
 ) -> Element {
+    let next_cursor = page.read().next_cursor.clone();
     rsx! {
-        if *offset.read() + 20 < *count_of_cats.read() {
+        if let Some(next_cursor) = next_cursor {
             button {
-                onclick: move |_| async move {
+                onclick: move |_| {
                     is_loading.set(true);
-                    let curr = *offset.read();
-                    offset.set(curr + 20);
-                    favorites.restart();
+                    page_no += 1;
+                    cursor.set(Some(next_cursor.clone()));
                 },
                 id: "handright",
                 "👉"
@@ -116,27 +746,202 @@ pub fn HandRight(
     }
 }
 
+/// the component of the export menu
+#[component]
+pub fn FavoritesExport() -> Element {
+    let mut is_open = use_signal(|| false);
+    let mut message = use_signal(String::new);
+    rsx! {
+        div { id: "favorites-export",
+            button {
+                onclick: move |_| {
+                    message.set(String::new());
+                    is_open.toggle();
+                },
+                id: "export",
+                "Export"
+            }
+            if *is_open.read() {
+                div { class: "export-menu",
+                    for (format , label) in crate::backends::EXPORT_FORMATS.iter() {
+                        ExportItem {
+                            key: "{format}",
+                            format,
+                            label,
+                            on_close: move |_| is_open.set(false),
+                            on_message: move |s| message.set(s),
+                        }
+                    }
+                    if !message.read().is_empty() {
+                        div { class: "export-message", "{message}" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// the item of the export menu, a download link of the session cookie
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+#[component]
+fn ExportItem(
+    format: &'static str,
+    label: &'static str,
+    on_close: EventHandler<()>,
+    on_message: EventHandler<String>,
+) -> Element {
+    let _ = on_message;
+    rsx! {
+        a {
+            href: crate::backends::export_url(format),
+            download: "",
+            onclick: move |_| on_close.call(()),
+            "{label}"
+        }
+    }
+}
+
+/// the item of the export menu, that the app saves in the download directory
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+#[component]
+fn ExportItem(
+    format: &'static str,
+    label: &'static str,
+    on_close: EventHandler<()>,
+    on_message: EventHandler<String>,
+) -> Element {
+    let _ = on_close;
+    let mut is_saving = use_signal(|| false);
+    rsx! {
+        button {
+            disabled: is_saving(),
+            onclick: move |_| async move {
+                is_saving.set(true);
+                on_message.call(String::new());
+                match crate::backends::download_cats(format).await {
+                    Ok(path) => on_message.call(format!("saved: {path}")),
+                    Err(e) => on_message.call(format!("error: {e}")),
+                }
+                is_saving.set(false);
+            },
+            "{label}"
+        }
+    }
+}
+
+/// the component of the import form
+#[component]
+pub fn FavoritesImport(reload: Signal<u32>, is_loading: Signal<bool>) -> Element {
+    let mut is_open = use_signal(|| false);
+    let mut message = use_signal(String::new);
+    rsx! {
+        div { id: "favorites-import",
+            button { onclick: move |_| is_open.toggle(), id: "import", "Import" }
+            if *is_open.read() {
+                form {
+                    class: "import-menu",
+                    onsubmit: move |evt: FormEvent| async move {
+                        evt.prevent_default();
+                        is_loading.set(true);
+                        let r = crate::backends::import_cats(evt.into()).await;
+                        is_loading.set(false);
+                        match r {
+                            Ok(r) => {
+                                let s = format!(
+                                    "added: {}, skipped: {}, rejected: {}",
+                                    r.added,
+                                    r.skipped,
+                                    r.rejected,
+                                );
+                                message.set(s);
+                                reload += 1;
+                            }
+                            Err(e) => message.set(format!("error: {e}")),
+                        }
+                    },
+                    input {
+                        r#type: "file",
+                        name: "file",
+                        accept: ".txt,.jsonl,.json,.csv",
+                    }
+                    button { r#type: "submit", "upload" }
+                    if !message.read().is_empty() {
+                        div { class: "import-message", "{message}" }
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[component]
 pub fn FavoriteCat(
-    id: i64,
-    url: String,
-    count_of_cats: Loader<usize>,
-    favorites: Loader<Vec<(i64, String)>>,
-    is_loading: Signal<bool>,
+    cat: CatItem,
+    is_selecting: bool,
+    is_selected: bool,
+    on_select: EventHandler<bool>,
+    on_open: EventHandler<()>,
+    on_delete: EventHandler<i64>,
 ) -> Element {
+    let id = cat.id;
+    // Keep the real aspect ratio, so that the layout doesn't jump while loading
+    let style = match (cat.width, cat.height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => format!("aspect-ratio: {w} / {h}"),
+        _ => String::new(),
+    };
     // Render a div for each photo using the cat's ID as the list key
     rsx! {
-        div { key: "{id}", class: "favorite-cat",
-            img { src: "{url}" }
-            button {
-                onclick: move |_| async move {
-                    is_loading.set(true);
-                    _ = crate::backends::delete_cat(id).await;
-                    count_of_cats.restart();
-                    favorites.restart();
+        div {
+            key: "{id}",
+            class: if is_selected { "favorite-cat selected" } else { "favorite-cat" },
+            // The thumbnail is shown, and the original is opened in the lightbox
+            a {
+                href: image_src(&cat.url),
+                target: "_blank",
+                onclick: move |evt| {
+                    evt.prevent_default();
+                    // The shift key selects the range from the last selected cat
+                    if is_selecting {
+                        on_select.call(evt.modifiers().shift());
+                    } else {
+                        on_open.call(());
+                    }
                 },
-                id: "delete",
-                "🚫"
+                if let Some(hash) = &cat.image_hash {
+                    img {
+                        src: thumb_url(hash, 320),
+                        srcset: thumb_srcset(hash),
+                        sizes: "300px",
+                        style,
+                    }
+                } else {
+                    img { src: image_src(&cat.url), style }
+                }
+            }
+            if cat.album.is_some() || !cat.tags.is_empty() {
+                div { class: "cat-labels",
+                    if let Some(album) = &cat.album {
+                        span { class: "cat-album", "📁 {album}" }
+                    }
+                    for tag in cat.tags.iter() {
+                        span { key: "{tag}", class: "cat-tag", "#{tag}" }
+                    }
+                }
+            }
+            if is_selecting {
+                span { class: "select-mark",
+                    if is_selected {
+                        "✅"
+                    } else {
+                        "⬜"
+                    }
+                }
+            } else {
+                button {
+                    onclick: move |_| on_delete.call(id),
+                    id: "delete",
+                    "🚫"
+                }
             }
         }
     }
@@ -0,0 +1,167 @@
+use crate::backends::PairingCode;
+use crate::{clear_identity, keep_identity, OverlaySpinner, Route};
+use dioxus::prelude::*;
+
+/// the component of the `Login page`
+///
+/// The account is optional, the cats of this device are kept without it.
+#[component]
+pub fn Login() -> Element {
+    let mut is_loading = use_signal(|| false);
+    let mut user = use_resource(|| async { crate::backends::current_user().await.ok().flatten() });
+    let mut name = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut attach = use_signal(|| true);
+    let mut message = use_signal(String::new);
+    let current = user.cloned().flatten();
+
+    rsx! {
+        div { id: "login",
+            if let Some(current) = current {
+                p { "logged in as " b { "{current}" } }
+                button {
+                    onclick: move |_| async move {
+                        is_loading.set(true);
+                        let _ = crate::backends::logout().await;
+                        clear_identity().await;
+                        // A new identity is issued by `Info` at the reload
+                        let _ = document::eval("location.reload();").await;
+                    },
+                    id: "logout",
+                    "log out"
+                }
+            } else {
+                form {
+                    id: "login-form",
+                    onsubmit: move |evt: FormEvent| async move {
+                        evt.prevent_default();
+                        is_loading.set(true);
+                        let r = crate::backends::login(
+                                name.cloned(),
+                                password.cloned(),
+                                *attach.read(),
+                            )
+                            .await;
+                        is_loading.set(false);
+                        match r {
+                            Ok(identity) => {
+                                // The cached favorites are of the other owner
+                                keep_identity(identity).await;
+                                navigator().push(Route::Favorites {});
+                            }
+                            Err(e) => message.set(format!("error: {e}")),
+                        }
+                    },
+                    input {
+                        r#type: "text",
+                        placeholder: "name",
+                        autocomplete: "username",
+                        value: "{name}",
+                        oninput: move |evt| name.set(evt.value()),
+                    }
+                    input {
+                        r#type: "password",
+                        placeholder: "password",
+                        autocomplete: "current-password",
+                        value: "{password}",
+                        oninput: move |evt| password.set(evt.value()),
+                    }
+                    label {
+                        input {
+                            r#type: "checkbox",
+                            checked: *attach.read(),
+                            onchange: move |evt| attach.set(evt.checked()),
+                        }
+                        "attach the cats of this device"
+                    }
+                    div { id: "login-buttons",
+                        button { r#type: "submit", id: "do-login", "log in" }
+                        button {
+                            r#type: "button",
+                            id: "register",
+                            onclick: move |_| async move {
+                                is_loading.set(true);
+                                let r = crate::backends::register(name.cloned(), password.cloned())
+                                    .await;
+                                is_loading.set(false);
+                                match r {
+                                    Ok(()) => {
+                                        message.set("registered".to_string());
+                                        user.restart();
+                                    }
+                                    Err(e) => message.set(format!("error: {e}")),
+                                }
+                            },
+                            "register"
+                        }
+                    }
+                }
+            }
+            if !message.read().is_empty() {
+                div { class: "login-message", "{message}" }
+            }
+            DeviceLink { is_loading }
+            Link { id: "sessions-link", to: Route::Sessions {}, "sessions of devices" }
+        }
+        if *is_loading.read() {
+            OverlaySpinner {}
+        }
+    }
+}
+
+/// the component to link the devices by a pairing code, without an account
+#[component]
+pub fn DeviceLink(is_loading: Signal<bool>) -> Element {
+    let mut pairing_code = use_signal(|| None::<PairingCode>);
+    let mut code = use_signal(String::new);
+    let mut message = use_signal(String::new);
+    rsx! {
+        div { id: "device-link",
+            h3 { "link devices" }
+            if let Some(pc) = pairing_code.cloned() {
+                p {
+                    "enter this code on the other device: "
+                    b { class: "pairing-code", "{pc.code}" }
+                    " (expires in {pc.expires_in / 60} minutes)"
+                }
+            } else {
+                button {
+                    onclick: move |_| async move {
+                        match crate::backends::create_pairing_code().await {
+                            Ok(pc) => pairing_code.set(Some(pc)),
+                            Err(e) => message.set(format!("error: {e}")),
+                        }
+                    },
+                    id: "show-pairing-code",
+                    "show a pairing code"
+                }
+            }
+            form {
+                id: "link-form",
+                onsubmit: move |evt: FormEvent| async move {
+                    evt.prevent_default();
+                    is_loading.set(true);
+                    let r = crate::backends::link_device(code.cloned()).await;
+                    is_loading.set(false);
+                    match r {
+                        Ok(identity) => {
+                            keep_identity(identity).await;
+                            navigator().push(Route::Favorites {});
+                        }
+                        Err(e) => message.set(format!("error: {e}")),
+                    }
+                },
+                input {
+                    r#type: "text",
+                    placeholder: "pairing code",
+                    value: "{code}",
+                    oninput: move |evt| code.set(evt.value()),
+                }
+                button { r#type: "submit", id: "link", "link this device" }
+            }
+            if !message.read().is_empty() {
+                div { class: "login-message", "{message}" }
+            }
+        }
+    }
+}
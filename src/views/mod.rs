@@ -3,3 +3,15 @@ pub use catview::*;
 
 mod favorites;
 pub use favorites::*;
+
+mod login;
+pub use login::*;
+
+mod sessions;
+pub use sessions::*;
+
+mod settings;
+pub use settings::*;
+
+mod trash;
+pub use trash::*;
@@ -0,0 +1,108 @@
+use crate::backends::DeviceSession;
+use crate::{clear_identity, OverlaySpinner};
+use dioxus::prelude::*;
+
+/// the component of the `Sessions page`, to list and revoke the sessions of devices
+#[component]
+pub fn Sessions() -> Element {
+    let mut is_loading = use_signal(|| false);
+    let mut sessions = use_resource(|| async { crate::backends::list_sessions().await.ok() });
+    let mut message = use_signal(String::new);
+
+    rsx! {
+        div { id: "sessions",
+            h3 { "sessions of devices" }
+            if let Some(list) = sessions.cloned().flatten() {
+                table { id: "sessions-table",
+                    tr {
+                        th { "device" }
+                        th { "os" }
+                        th { "created" }
+                        th { "last seen" }
+                        th {}
+                    }
+                    for s in list {
+                        SessionRow {
+                            key: "{s.sid}",
+                            s,
+                            sessions,
+                            message,
+                            is_loading,
+                        }
+                    }
+                }
+                button {
+                    onclick: move |_| async move {
+                        is_loading.set(true);
+                        match crate::backends::logout_everywhere().await {
+                            Ok(true) => revoked_this_device().await,
+                            Ok(false) => {
+                                message.set("the other devices are logged out".to_string());
+                                sessions.restart();
+                            }
+                            Err(e) => message.set(format!("error: {e}")),
+                        }
+                        is_loading.set(false);
+                    },
+                    id: "logout-everywhere",
+                    "log out everywhere"
+                }
+            }
+            if !message.read().is_empty() {
+                div { class: "login-message", "{message}" }
+            }
+        }
+        if *is_loading.read() {
+            OverlaySpinner {}
+        }
+    }
+}
+
+/// the component of a row of `Sessions`
+#[component]
+fn SessionRow(
+    s: DeviceSession,
+    sessions: Resource<Option<Vec<DeviceSession>>>,
+    message: Signal<String>,
+    is_loading: Signal<bool>,
+) -> Element {
+    let sid = s.sid.clone();
+    let is_current = s.is_current;
+    rsx! {
+        tr { class: if is_current { "session-current" },
+            td {
+                "{s.browser}"
+                if is_current {
+                    " (this device)"
+                }
+            }
+            td { "{s.os}" }
+            td { "{s.create_at}" }
+            td { "{s.last_seen_at}" }
+            td {
+                if !is_current {
+                    button {
+                        onclick: move |_| {
+                            let sid = sid.clone();
+                            async move {
+                                is_loading.set(true);
+                                match crate::backends::revoke_session(sid).await {
+                                    Ok(()) => sessions.restart(),
+                                    Err(e) => message.set(format!("error: {e}")),
+                                }
+                                is_loading.set(false);
+                            }
+                        },
+                        "revoke"
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The identity of this device is gone, a new one is issued by `Info` at the reload
+async fn revoked_this_device() {
+    clear_identity().await;
+    let _ = document::eval("location.reload();").await;
+}
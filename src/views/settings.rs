@@ -0,0 +1,131 @@
+use dioxus::prelude::*;
+
+/// the component of the `Settings page`, to change the backend server of the app
+#[component]
+pub fn Settings() -> Element {
+    rsx! {
+        div { id: "settings",
+            h3 { "settings" }
+            BackendSettings {}
+        }
+    }
+}
+
+/// the component of the backend server of `Settings`
+///
+/// The url is used at the next launch, because the backend calls are connected at the launch.
+#[cfg(any(feature = "desktop", feature = "mobile"))]
+#[component]
+fn BackendSettings() -> Element {
+    use crate::settings::{
+        check_backend, load_settings, normalize_backend_url, save_settings, DEFAULT_BACKEND_URL,
+    };
+    use crate::OverlaySpinner;
+
+    let mut is_loading = use_signal(|| false);
+    let mut url = use_signal(|| load_settings().backend_url().to_string());
+    let local_server = use_signal(|| load_settings().local_server);
+    let mut message = use_signal(String::new);
+    let current = dioxus_fullstack::get_server_url();
+
+    rsx! {
+        p { "connected to " b { "{current}" } }
+        form {
+            id: "settings-form",
+            onsubmit: move |evt: FormEvent| {
+                evt.prevent_default();
+                let r = normalize_backend_url(&url.read()).and_then(|backend_url| {
+                    let mut settings = load_settings();
+                    settings.backend_url = (backend_url != DEFAULT_BACKEND_URL)
+                        .then(|| backend_url.clone());
+                    settings.local_server = *local_server.read();
+                    save_settings(&settings)?;
+                    Ok(backend_url)
+                });
+                match r {
+                    Ok(backend_url) => {
+                        url.set(backend_url);
+                        message.set("saved, restart the app to connect to the server".to_string());
+                    }
+                    Err(e) => message.set(format!("error: {e}")),
+                }
+            },
+            label { r#for: "backend-url", "backend server" }
+            input {
+                id: "backend-url",
+                r#type: "url",
+                placeholder: "{DEFAULT_BACKEND_URL}",
+                value: "{url}",
+                oninput: move |evt| url.set(evt.value()),
+            }
+            LocalServerOption { local_server }
+            div { id: "settings-buttons",
+                button {
+                    r#type: "button",
+                    onclick: move |_| async move {
+                        is_loading.set(true);
+                        let r = check_backend(&url.cloned()).await;
+                        is_loading.set(false);
+                        match r {
+                            Ok(info) => message.set(format!("connected: {} {}", info.name, info.version)),
+                            Err(e) => message.set(format!("error: {e}")),
+                        }
+                    },
+                    "check connection"
+                }
+                button {
+                    r#type: "button",
+                    onclick: move |_| url.set(DEFAULT_BACKEND_URL.to_string()),
+                    "default"
+                }
+                button { r#type: "submit", "save" }
+            }
+        }
+        if !message.read().is_empty() {
+            div { class: "login-message", "{message}" }
+        }
+        if *is_loading.read() {
+            OverlaySpinner {}
+        }
+    }
+}
+
+/// the component of the option of the local server, on the desktop app
+///
+/// The local server keeps the cats in the data directory, then the app works offline.
+#[cfg(feature = "desktop")]
+#[component]
+fn LocalServerOption(local_server: Signal<bool>) -> Element {
+    let data_dir = crate::local_server::local_data_dir()
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_default();
+    rsx! {
+        label {
+            input {
+                r#type: "checkbox",
+                checked: *local_server.read(),
+                onchange: move |evt| local_server.set(evt.checked()),
+            }
+            "use the local server on this computer"
+        }
+        if *local_server.read() {
+            div { class: "settings-note", "the cats are kept in {data_dir}" }
+        }
+    }
+}
+
+#[cfg(all(feature = "mobile", not(feature = "desktop")))]
+#[component]
+fn LocalServerOption(local_server: Signal<bool>) -> Element {
+    let _ = local_server;
+    rsx! {}
+}
+
+/// the component of the backend server of `Settings`, that is the web server itself
+#[cfg(not(any(feature = "desktop", feature = "mobile")))]
+#[component]
+fn BackendSettings() -> Element {
+    rsx! {
+        p { "the backend server is this web server" }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::backends::{image_src, thumb_url, TrashItem, TrashList};
+use crate::OverlaySpinner;
+use dioxus::prelude::*;
+
+/// the component of the `Trash page`, to restore or delete permanently the deleted cats
+#[component]
+pub fn Trash() -> Element {
+    let is_loading = use_signal(|| false);
+    let trash = use_resource(|| async { crate::backends::list_trash().await.ok() });
+    let message = use_signal(String::new);
+
+    rsx! {
+        div { id: "trash",
+            h3 { "trash" }
+            if let Some(list) = trash.cloned().flatten() {
+                div { class: "settings-note",
+                    "{list.items.len()} cats in the trash"
+                    if list.purge_days > 0 {
+                        ", they are deleted permanently after {list.purge_days} days"
+                    }
+                }
+                div { id: "trash-container",
+                    for item in list.items {
+                        TrashCat {
+                            key: "{item.cat.id}",
+                            item,
+                            trash,
+                            message,
+                            is_loading,
+                        }
+                    }
+                }
+            } else if trash.read().is_some() {
+                div { class: "login-message", "the trash is not reachable" }
+            }
+            if !message.read().is_empty() {
+                div { class: "login-message", "{message}" }
+            }
+        }
+        if *is_loading.read() || trash.read().is_none() {
+            OverlaySpinner {}
+        }
+    }
+}
+
+/// the component of a cat of `Trash`
+#[component]
+fn TrashCat(
+    item: TrashItem,
+    trash: Resource<Option<TrashList>>,
+    message: Signal<String>,
+    is_loading: Signal<bool>,
+) -> Element {
+    let id = item.cat.id;
+    // The permanent deletion needs the second click
+    let mut is_armed = use_signal(|| false);
+    let src = match &item.cat.image_hash {
+        Some(hash) => thumb_url(hash, 320),
+        None => image_src(&item.cat.url),
+    };
+    rsx! {
+        div { class: "trash-cat",
+            img { src }
+            div { class: "trash-deleted-at", "{item.deleted_at}" }
+            div {
+                button {
+                    onclick: move |_| async move {
+                        is_loading.set(true);
+                        match crate::backends::restore_cat(id).await {
+                            Ok(_) => trash.restart(),
+                            Err(e) => message.set(format!("error: {e}")),
+                        }
+                        is_loading.set(false);
+                    },
+                    "restore"
+                }
+                button {
+                    onclick: move |_| async move {
+                        if !is_armed() {
+                            is_armed.set(true);
+                            return;
+                        }
+                        is_loading.set(true);
+                        match crate::backends::purge_cat(id).await {
+                            Ok(_) => trash.restart(),
+                            Err(e) => message.set(format!("error: {e}")),
+                        }
+                        is_loading.set(false);
+                    },
+                    if is_armed() {
+                        "really delete?"
+                    } else {
+                        "delete permanently"
+                    }
+                }
+            }
+        }
+    }
+}